@@ -0,0 +1,83 @@
+use ark_bn254::Bn254;
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_serialize::{CanonicalSerialize, CanonicalDeserialize};
+use std::fs::File;
+use std::fs;
+use std::io::BufReader;
+
+/// The key files of one circuit: `{dir}{name}.pk.dat`, `{dir}{name}.vk.dat` and their
+/// uncompressed forms. Every circuit module names its own in its `setup`.
+#[derive(Clone, Copy, Debug)]
+pub struct KeyFiles {
+    pub dir: &'static str,
+    pub name: &'static str,
+}
+
+impl KeyFiles {
+    pub const fn new(dir: &'static str, name: &'static str) -> Self {
+        Self { dir, name }
+    }
+
+    pub fn pk_file(&self) -> String {
+        format!("{}{}.pk.dat", self.dir, self.name)
+    }
+
+    pub fn vk_file(&self) -> String {
+        format!("{}{}.vk.dat", self.dir, self.name)
+    }
+
+    pub fn pk_uncompressed_file(&self) -> String {
+        format!("{}{}.pk.uncompressed.dat", self.dir, self.name)
+    }
+
+    pub fn vk_uncompressed_file(&self) -> String {
+        format!("{}{}.vk.uncompressed.dat", self.dir, self.name)
+    }
+
+    pub fn proof_file(&self) -> String {
+        format!("{}{}.proof.dat", self.dir, self.name)
+    }
+
+    pub fn store_pk_vk(&self, pk: ProvingKey<Bn254>, vk: VerifyingKey<Bn254>) {
+        let mut pk_bytes = Vec::new();
+        pk.serialize_compressed(&mut pk_bytes).unwrap();
+
+        let mut pk_bytes_uncompressed = Vec::new();
+        pk.serialize_uncompressed(&mut pk_bytes_uncompressed)
+            .unwrap();
+
+        let mut vk_bytes = Vec::new();
+        vk.serialize_compressed(&mut vk_bytes).unwrap();
+
+        let mut vk_bytes_uncompressed = Vec::new();
+        vk.serialize_uncompressed(&mut vk_bytes_uncompressed)
+            .unwrap();
+
+        fs::write(self.pk_file(), pk_bytes).unwrap();
+        fs::write(self.vk_file(), vk_bytes).unwrap();
+        fs::write(self.pk_uncompressed_file(), pk_bytes_uncompressed).unwrap();
+        fs::write(self.vk_uncompressed_file(), vk_bytes_uncompressed).unwrap();
+    }
+
+    pub fn load_pk(&self) -> ProvingKey<Bn254> {
+        read_proving_key(&self.pk_file()).expect("Failed to read proving key")
+    }
+
+    pub fn load_vk(&self) -> VerifyingKey<Bn254> {
+        read_verifying_key(&self.vk_file()).expect("Failed to read verifying key")
+    }
+}
+
+pub fn read_proving_key(file_path: &str) -> Result<ProvingKey<Bn254>, ark_serialize::SerializationError> {
+    let file = File::open(file_path)?;
+    let mut reader = BufReader::new(file);
+    let pk = ProvingKey::<Bn254>::deserialize_compressed(&mut reader)?;
+    Ok(pk)
+}
+
+pub fn read_verifying_key(file_path: &str) -> Result<VerifyingKey<Bn254>, ark_serialize::SerializationError> {
+    let file = File::open(file_path)?;
+    let mut reader = BufReader::new(file);
+    let vk = VerifyingKey::<Bn254>::deserialize_compressed(&mut reader)?;
+    Ok(vk)
+}
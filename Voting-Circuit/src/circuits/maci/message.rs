@@ -0,0 +1,186 @@
+use ark_crypto_primitives::{crh::{poseidon::CRH, CRHScheme}, sponge::poseidon::PoseidonConfig};
+use ark_ec::{twisted_edwards::TECurveConfig, AffineRepr, CurveGroup};
+use ark_ed_on_bn254::{EdwardsAffine, EdwardsConfig, Fq, Fr};
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_std::UniformRand;
+use rand::Rng;
+
+/// Number of field elements in the plaintext of a message:
+/// `[state_index, new_pk.x, new_pk.y, vote_option, nonce, R.x, R.y, s]`.
+pub const MESSAGE_LEN: usize = 8;
+
+/// A vote or key change of the voter at `state_index`. Keeping the key is a key change to the same key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Command {
+    pub state_index: u64,
+    pub new_pk: EdwardsAffine,
+    pub vote_option: u64,
+    pub nonce: u64,  // number of valid commands of the voter so far, plus one
+}
+
+/// A command encrypted to the coordinator under the ECDH key of `enc_pk`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub enc_pk: EdwardsAffine,
+    pub data: Vec<Fq>,
+}
+
+/// The state of a voter. `nonce` is zero until the first valid command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateLeaf {
+    pub pk: EdwardsAffine,
+    pub vote_option: Fq,
+    pub nonce: Fq,
+}
+
+fn to_scalar(x: Fq) -> Fr {
+    Fr::from_le_bytes_mod_order(&x.into_bigint().to_bytes_le())
+}
+
+/// Whether `(x, y)` satisfies `a x^2 + y^2 = 1 + d x^2 y^2`.
+pub fn is_on_curve(x: Fq, y: Fq) -> bool {
+    let (x2, y2) = (x.square(), y.square());
+    EdwardsConfig::COEFF_A * x2 + y2 == Fq::ONE + EdwardsConfig::COEFF_D * x2 * y2
+}
+
+/// Whether `pk` can become a voter key: in the prime-order subgroup and not the identity,
+/// so that it has a secret key and no small-order component.
+pub fn is_valid_key(pk: EdwardsAffine) -> bool {
+    !pk.is_zero() && pk.is_in_correct_subgroup_assuming_on_curve()
+}
+
+/// The point `(x, y)`, or `g` when it is not on the curve, as the processing circuit reads it.
+pub fn to_point(g: EdwardsAffine, x: Fq, y: Fq) -> EdwardsAffine {
+    if is_on_curve(x, y) {
+        EdwardsAffine::new_unchecked(x, y)
+    } else {
+        g
+    }
+}
+
+pub fn ecdh(sk: Fq, pk: EdwardsAffine) -> EdwardsAffine {
+    pk.mul_bigint(sk.into_bigint()).into_affine()
+}
+
+fn keystream(hash_params: &PoseidonConfig<Fq>, shared: EdwardsAffine) -> Vec<Fq> {
+    (0..MESSAGE_LEN)
+        .map(|i| CRH::<Fq>::evaluate(hash_params, vec![shared.x, shared.y, Fq::from(i as u64)]).unwrap())
+        .collect()
+}
+
+/// The challenge `e` of the signature `(R, s)` by `pk` on a plaintext.
+pub fn challenge(hash_params: &PoseidonConfig<Fq>, pk: EdwardsAffine, plaintext: &[Fq]) -> Fq {
+    let mut hash_input = vec![plaintext[5], plaintext[6], pk.x, pk.y];
+    hash_input.extend_from_slice(&plaintext[..5]);
+    CRH::<Fq>::evaluate(hash_params, hash_input).unwrap()
+}
+
+/// Checks the Schnorr signature `g^s = R + pk^e` in a plaintext.
+pub fn verify_signature(g: EdwardsAffine, hash_params: &PoseidonConfig<Fq>, pk: EdwardsAffine, plaintext: &[Fq]) -> bool {
+    let r = to_point(g, plaintext[5], plaintext[6]);
+    let e = challenge(hash_params, pk, plaintext);
+    g.mul_bigint(plaintext[7].into_bigint()) == r.into_group() + pk.mul_bigint(e.into_bigint())
+}
+
+impl Command {
+    /// Signs the command with `sk` and lays it out as a plaintext of `MESSAGE_LEN` elements.
+    pub fn sign<R: Rng>(&self, g: EdwardsAffine, hash_params: &PoseidonConfig<Fq>, sk: Fq, rng: &mut R) -> Vec<Fq> {
+        let k = Fq::rand(rng);
+        let r = g.mul_bigint(k.into_bigint()).into_affine();
+        let pk = g.mul_bigint(sk.into_bigint()).into_affine();
+
+        let mut plaintext = vec![
+            Fq::from(self.state_index),
+            self.new_pk.x,
+            self.new_pk.y,
+            Fq::from(self.vote_option),
+            Fq::from(self.nonce),
+            r.x,
+            r.y,
+        ];
+        let e = challenge(hash_params, pk, &plaintext);
+        let s = to_scalar(k) + to_scalar(e) * to_scalar(sk);
+        plaintext.push(Fq::from_le_bytes_mod_order(&s.into_bigint().to_bytes_le()));
+        plaintext
+    }
+}
+
+impl Message {
+    /// Encrypts a plaintext to `coord_pk` with a fresh ephemeral key.
+    pub fn encrypt<R: Rng>(g: EdwardsAffine, hash_params: &PoseidonConfig<Fq>, coord_pk: EdwardsAffine, plaintext: &[Fq], rng: &mut R) -> Self {
+        let esk = Fq::rand(rng);
+        let enc_pk = g.mul_bigint(esk.into_bigint()).into_affine();
+        let key = keystream(hash_params, ecdh(esk, coord_pk));
+        let data = plaintext.iter().zip(key.iter()).map(|(m, k)| *m + k).collect();
+        Self { enc_pk, data }
+    }
+
+    pub fn decrypt(&self, g: EdwardsAffine, hash_params: &PoseidonConfig<Fq>, coord_sk: Fq) -> Vec<Fq> {
+        let shared = ecdh(coord_sk, to_point(g, self.enc_pk.x, self.enc_pk.y));
+        let key = keystream(hash_params, shared);
+        self.data.iter().zip(key.iter()).map(|(c, k)| *c - k).collect()
+    }
+}
+
+/// The state before any message: the registered keys, in registration order, without a vote.
+pub fn initial_state(pks: &[EdwardsAffine]) -> Vec<StateLeaf> {
+    pks.iter().map(|pk| StateLeaf {
+        pk: *pk,
+        vote_option: Fq::ZERO,
+        nonce: Fq::ZERO,
+    }).collect()
+}
+
+pub fn state_commitment(hash_params: &PoseidonConfig<Fq>, state: &[StateLeaf]) -> Fq {
+    let hash_input = state.iter().flat_map(|leaf| [leaf.pk.x, leaf.pk.y, leaf.vote_option, leaf.nonce]).collect::<Vec<_>>();
+    CRH::<Fq>::evaluate(hash_params, hash_input).unwrap()
+}
+
+/// Applies the messages in order, skipping invalid ones, as the processing circuit does.
+/// A command is valid if it targets an existing voter, carries the next nonce, holds
+/// points on the curve and a `new_pk` that passes `is_valid_key`, and is signed by the
+/// voter's current key.
+pub fn process_messages(
+    g: EdwardsAffine,
+    hash_params: &PoseidonConfig<Fq>,
+    coord_sk: Fq,
+    state: &[StateLeaf],
+    messages: &[Message],
+) -> Vec<StateLeaf> {
+    let mut state = state.to_vec();
+    for message in messages.iter() {
+        let plaintext = message.decrypt(g, hash_params, coord_sk);
+        let Some(leaf) = state.iter_mut().enumerate().find(|(i, _)| Fq::from(*i as u64) == plaintext[0]).map(|(_, leaf)| leaf) else {
+            continue;
+        };
+        if !is_on_curve(plaintext[1], plaintext[2]) || !is_on_curve(plaintext[5], plaintext[6]) {
+            continue;
+        }
+        if !is_valid_key(EdwardsAffine::new_unchecked(plaintext[1], plaintext[2])) {
+            continue;
+        }
+        if plaintext[4] != leaf.nonce + Fq::ONE || !verify_signature(g, hash_params, leaf.pk, &plaintext) {
+            continue;
+        }
+        *leaf = StateLeaf {
+            pk: EdwardsAffine::new_unchecked(plaintext[1], plaintext[2]),
+            vote_option: plaintext[3],
+            nonce: plaintext[4],
+        };
+    }
+    state
+}
+
+/// Number of voters whose last valid command chose each of `num_of_options` options.
+/// Voters without a valid command and options out of range are not counted.
+pub fn count_votes(state: &[StateLeaf], num_of_options: u64) -> Vec<u64> {
+    let mut count = vec![0; num_of_options as usize];
+    for leaf in state.iter().filter(|leaf| leaf.nonce != Fq::ZERO) {
+        for (i, count_i) in count.iter_mut().enumerate() {
+            if leaf.vote_option == Fq::from(i as u64) {
+                *count_i += 1;
+            }
+        }
+    }
+    count
+}
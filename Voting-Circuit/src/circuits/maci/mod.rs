@@ -0,0 +1,23 @@
+pub mod process_circuit;
+pub mod message;
+pub mod tally_circuit;
+pub mod test;
+pub mod prover;
+pub mod setup;
+
+use ark_ed_on_bn254::{EdwardsAffine, Fq};
+
+use crate::circuits::maci::message::Command;
+use crate::Error;
+
+pub trait MockingCircuit {
+    type Output;
+
+    fn generate_circuit(
+        g: EdwardsAffine,
+        coord_sk: Fq,
+        tree_height: u64,
+        num_of_voters: u64,
+        commands: Vec<(Fq, Command)>,
+    ) -> Result<Self::Output, Error>;
+}
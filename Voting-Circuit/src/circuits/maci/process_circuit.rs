@@ -0,0 +1,259 @@
+use ark_crypto_primitives::{
+    crh::{poseidon::constraints::{CRHGadget, CRHParametersVar}, CRHSchemeGadget}, sponge::poseidon::PoseidonConfig
+};
+use ark_ec::{twisted_edwards::TECurveConfig, AffineRepr, CurveGroup};
+use ark_ed_on_bn254::{constraints::EdwardsVar, EdwardsAffine, EdwardsConfig, Fq, Fr};
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::UniformRand;
+use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use rand::thread_rng;
+use crate::circuits::maci::message::{initial_state, process_messages, state_commitment, Command, Message, MESSAGE_LEN};
+use crate::circuits::maci::MockingCircuit;
+use crate::circuits::voting::merkle_tree::{incremental_root_gadget, IncrementalMerkleTree};
+use crate::circuits::voting::poseidon_params::get_poseidon_params;
+use crate::circuits::voting::registry::{empty_voter_leaf, identity_commitment};
+
+type GG = EdwardsVar;
+
+#[derive(Clone, Debug)]
+pub struct ProcessInstance {
+    pub coord_pk: Option<EdwardsAffine>,
+    pub voter_root: Option<Fq>,  // root of the voter registry, whose keys start the state
+    pub final_state_cm: Option<Fq>,
+    pub messages: Option<Vec<Message>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProcessWitness {
+    pub coord_sk: Option<Fq>,
+    pub pks: Option<Vec<EdwardsAffine>>,  // registered keys, in registration order
+    pub salts: Option<Vec<Fq>>,  // salts of their commitments in the registry
+}
+
+/// Proves that the coordinator decrypted every message and applied the valid ones in order.
+///
+/// The state starts from every key in the voter registry, an `IncrementalMerkleTree` of
+/// `tree_height` over `identity_commitment`s, with no vote. `final_state_cm` is the state
+/// commitment that `StateTallyCircuit` counts.
+///
+/// The circuit works on the Baby Jubjub curve only: the points in a message are read from
+/// their decrypted coordinates, and a point off the curve makes the message invalid
+/// instead of the circuit unsatisfiable.
+#[derive(Clone, Debug)]
+pub struct ProcessCircuit {
+    pub g: EdwardsAffine,
+    pub hash_params: PoseidonConfig<Fq>,
+    pub tree_height: u64,
+    pub instance: ProcessInstance,
+    pub witness: ProcessWitness,
+}
+
+impl ProcessCircuit {
+    pub fn new(
+        g: EdwardsAffine,
+        hash_params: PoseidonConfig<Fq>,
+        tree_height: u64,
+        instance: ProcessInstance,
+        witness: ProcessWitness,
+    ) -> Self {
+        Self {
+            g,
+            hash_params,
+            tree_height,
+            instance,
+            witness,
+        }
+    }
+}
+
+/// The point `(x, y)` if it is on the curve, and `g` otherwise.
+fn point_or_g(g: EdwardsAffine, x: &FpVar<Fq>, y: &FpVar<Fq>) -> Result<(GG, Boolean<Fq>), SynthesisError> {
+    let (x2, y2) = (x.square()?, y.square()?);
+    let lhs = &x2 * EdwardsConfig::COEFF_A + &y2;
+    let rhs = FpVar::one() + x2 * y2 * EdwardsConfig::COEFF_D;
+    let is_on_curve = lhs.is_eq(&rhs)?;
+
+    let x = is_on_curve.select(x, &FpVar::constant(g.x))?;
+    let y = is_on_curve.select(y, &FpVar::constant(g.y))?;
+    Ok((GG::new(x, y), is_on_curve))
+}
+
+/// Whether `pk` is in the prime-order subgroup, `pk^order = 0`, and not the identity.
+fn is_valid_key_gadget(pk: &GG) -> Result<Boolean<Fq>, SynthesisError> {
+    let order_bits = Fr::MODULUS.to_bits_le().into_iter().map(Boolean::constant).collect::<Vec<_>>();
+    let in_subgroup = pk.scalar_mul_le(order_bits.iter())?.is_zero()?;
+    in_subgroup.and(&pk.is_zero()?.not())
+}
+
+pub(crate) fn state_commitment_gadget(hash_params: &CRHParametersVar<Fq>, state: &[(GG, FpVar<Fq>, FpVar<Fq>)]) -> Result<FpVar<Fq>, SynthesisError> {
+    let hash_input = state.iter().flat_map(|(pk, vote_option, nonce)| [pk.x.clone(), pk.y.clone(), vote_option.clone(), nonce.clone()]).collect::<Vec<_>>();
+    CRHGadget::<Fq>::evaluate(hash_params, &hash_input)
+}
+
+impl ConstraintSynthesizer<Fq> for ProcessCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fq>) -> ark_relations::r1cs::Result<()> {
+        let g = GG::new_constant(cs.clone(), self.g)?;
+        let hash_params = CRHParametersVar::<Fq>::new_constant(cs.clone(), self.hash_params)?;
+
+        // instance
+        let coord_pk = GG::new_input(cs.clone(), || self.instance.coord_pk.ok_or(SynthesisError::AssignmentMissing))?;
+        let voter_root = FpVar::<Fq>::new_input(cs.clone(), || self.instance.voter_root.ok_or(SynthesisError::AssignmentMissing))?;
+        let final_state_cm = FpVar::<Fq>::new_input(cs.clone(), || self.instance.final_state_cm.ok_or(SynthesisError::AssignmentMissing))?;
+        let mut messages = vec![];
+        for message in self.instance.messages.ok_or(SynthesisError::AssignmentMissing)? {
+            let enc_pk_x = FpVar::<Fq>::new_input(cs.clone(), || Ok(message.enc_pk.x))?;
+            let enc_pk_y = FpVar::<Fq>::new_input(cs.clone(), || Ok(message.enc_pk.y))?;
+            let data = Vec::<FpVar<Fq>>::new_input(cs.clone(), || Ok(message.data))?;
+            messages.push((enc_pk_x, enc_pk_y, data));
+        }
+
+        // witness
+        let coord_sk = FpVar::<Fq>::new_witness(cs.clone(), || self.witness.coord_sk.ok_or(SynthesisError::AssignmentMissing))?;
+        let pks = Vec::<GG>::new_witness(cs.clone(), || self.witness.pks.ok_or(SynthesisError::AssignmentMissing))?;
+        let salts = Vec::<FpVar<Fq>>::new_witness(cs.clone(), || self.witness.salts.ok_or(SynthesisError::AssignmentMissing))?;
+        if pks.len() != salts.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+
+        // Constraints
+
+        // 1. Check coord_pk = g^coord_sk
+        let coord_sk_bits = coord_sk.to_bits_le()?;
+        let coord_pk_computed = g.scalar_mul_le(coord_sk_bits.iter())?;
+        coord_pk_computed.enforce_equal(&coord_pk)?;
+
+
+        // 2. Check voter_root = root of the tree over cm_i = CRH(pk_i || salt_i),
+        //    and start every voter i at state_i = (pk_i, 0, 0)
+        let mut leaves = vec![];
+        for (pk, salt) in pks.iter().zip(salts.iter()) {
+            leaves.push(vec![CRHGadget::<Fq>::evaluate(&hash_params, &[pk.x.clone(), pk.y.clone(), salt.clone()])?]);
+        }
+        incremental_root_gadget(&hash_params, self.tree_height as usize, &empty_voter_leaf(false), &leaves)?.enforce_equal(&voter_root)?;
+        let mut state = pks.into_iter().map(|pk| (pk, FpVar::<Fq>::zero(), FpVar::<Fq>::zero())).collect::<Vec<_>>();
+
+
+        for (enc_pk_x, enc_pk_y, data) in messages.iter() {
+            if data.len() != MESSAGE_LEN {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+
+            // 3. plaintext[i] = data[i] - CRH(enc_pk^coord_sk || i)
+            let (enc_pk, _) = point_or_g(self.g, enc_pk_x, enc_pk_y)?;
+            let shared = enc_pk.scalar_mul_le(coord_sk_bits.iter())?;
+            let mut plaintext = vec![];
+            for (i, data_i) in data.iter().enumerate() {
+                let i = FpVar::<Fq>::constant(Fq::from(i as u64));
+                let key_i = CRHGadget::<Fq>::evaluate(&hash_params, &[shared.x.clone(), shared.y.clone(), i])?;
+                plaintext.push(data_i - key_i);
+            }
+
+
+            // 4. The command is valid: state_index exists, nonce = nonce_i + 1, new_pk and R are on the curve,
+            //    new_pk is in the prime-order subgroup and not the identity, g^s = R + pk_i^e with e = CRH(R || pk_i || command)
+            let mut is_target = vec![];
+            for i in 0..state.len() {
+                let i = FpVar::<Fq>::constant(Fq::from(i as u64));
+                is_target.push(plaintext[0].is_eq(&i)?);
+            }
+            let mut cur_pk = g.clone();
+            let mut cur_nonce = FpVar::<Fq>::zero();
+            for ((pk, _, nonce), is_target_i) in state.iter().zip(is_target.iter()) {
+                cur_pk = is_target_i.select(pk, &cur_pk)?;
+                cur_nonce = is_target_i.select(nonce, &cur_nonce)?;
+            }
+            let has_target = Boolean::kary_or(&is_target)?;
+
+            let (new_pk, new_pk_on_curve) = point_or_g(self.g, &plaintext[1], &plaintext[2])?;
+            let new_pk_ok = is_valid_key_gadget(&new_pk)?;
+            let (r, r_on_curve) = point_or_g(self.g, &plaintext[5], &plaintext[6])?;
+            let nonce_ok = plaintext[4].is_eq(&(&cur_nonce + FpVar::one()))?;
+
+            let mut hash_input = vec![plaintext[5].clone(), plaintext[6].clone(), cur_pk.x.clone(), cur_pk.y.clone()];
+            hash_input.extend_from_slice(&plaintext[..5]);
+            let e = CRHGadget::<Fq>::evaluate(&hash_params, &hash_input)?;
+            let g_s = g.scalar_mul_le(plaintext[7].to_bits_le()?.iter())?;
+            let r_pk_e = r + cur_pk.scalar_mul_le(e.to_bits_le()?.iter())?;
+            let signature_ok = g_s.is_eq(&r_pk_e)?;
+
+            let is_valid = Boolean::kary_and(&[has_target, new_pk_on_curve, new_pk_ok, r_on_curve, nonce_ok, signature_ok])?;
+
+
+            // 5. state_i = (new_pk, vote_option, nonce) if the command is valid and targets voter i
+            for ((pk, vote_option, nonce), is_target_i) in state.iter_mut().zip(is_target.iter()) {
+                let is_update = is_valid.and(is_target_i)?;
+                *pk = is_update.select(&new_pk, pk)?;
+                *vote_option = is_update.select(&plaintext[3], vote_option)?;
+                *nonce = is_update.select(&plaintext[4], nonce)?;
+            }
+        }
+
+
+        // 6. Check final_state_cm = CRH(state)
+        state_commitment_gadget(&hash_params, &state)?.enforce_equal(&final_state_cm)?;
+        Ok(())
+    }
+}
+
+
+impl MockingCircuit for ProcessCircuit {
+    type Output = ProcessCircuit;
+
+    fn generate_circuit(
+            g: EdwardsAffine,
+            coord_sk: Fq,
+            tree_height: u64,
+            num_of_voters: u64,
+            commands: Vec<(Fq, Command)>,  // signing key and command of every message
+        ) -> Result<Self::Output, crate::Error> {
+        use crate::circuits::voting::parser::*;
+
+        let mut rng = thread_rng();
+        let hash_params = get_poseidon_params::<Fq>();
+
+        // coordinator key
+        let coord_pk = g.mul_bigint(coord_sk.into_bigint()).into_affine();
+
+        // voter registry, in which mock voters commit to their keys with random salts
+        let users = parse_all_users()?;
+        if num_of_voters as usize > users.len() {
+            return Err("not enough mock users for num_of_voters".into());
+        }
+        let pks = users.iter().take(num_of_voters as usize).map(|user| user.pk).collect::<Vec<_>>();
+        let salts = pks.iter().map(|_| Fq::rand(&mut rng)).collect::<Vec<_>>();
+        let mut registry = IncrementalMerkleTree::new(hash_params.clone(), tree_height as usize, empty_voter_leaf(false), 1)?;
+        for (pk, salt) in pks.iter().zip(salts.iter()) {
+            registry.append(&[identity_commitment(&hash_params, pk.x, pk.y, *salt)?])?;
+        }
+
+        // signed-up voters, before any message
+        let state = initial_state(&pks);
+
+        // messages
+        let messages = commands.iter().map(|(sk, command)| {
+            let plaintext = command.sign(g, &hash_params, *sk, &mut rng);
+            Message::encrypt(g, &hash_params, coord_pk, &plaintext, &mut rng)
+        }).collect::<Vec<_>>();
+
+        let final_state = process_messages(g, &hash_params, coord_sk, &state, &messages);
+        let final_state_str = final_state.iter().map(|leaf| (leaf.vote_option.to_string(), leaf.nonce.to_string())).collect::<Vec<_>>();
+        println!("final state (vote_option, nonce): {:?}", final_state_str);
+
+        let instance = ProcessInstance {
+            coord_pk: Some(coord_pk),
+            voter_root: Some(registry.root()),
+            final_state_cm: Some(state_commitment(&hash_params, &final_state)),
+            messages: Some(messages),
+        };
+
+        let witness = ProcessWitness {
+            coord_sk: Some(coord_sk),
+            pks: Some(pks),
+            salts: Some(salts),
+        };
+
+        Ok(Self::new(g, hash_params, tree_height, instance, witness))
+    }
+}
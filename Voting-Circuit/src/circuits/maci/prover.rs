@@ -0,0 +1,20 @@
+use ark_bn254::Bn254;
+use ark_groth16::{Groth16, ProvingKey, Proof};
+use ark_std::rand::thread_rng;
+use ark_crypto_primitives::snark::SNARK;
+
+use super::process_circuit::ProcessCircuit;
+use super::tally_circuit::StateTallyCircuit;
+
+
+pub fn maci_prove(pk: ProvingKey<Bn254>, circuit: ProcessCircuit) -> Proof<Bn254> {
+    let rng = &mut thread_rng();
+
+    Groth16::<Bn254>::prove(&pk, circuit, rng).unwrap()
+}
+
+pub fn maci_tally_prove(pk: ProvingKey<Bn254>, circuit: StateTallyCircuit) -> Proof<Bn254> {
+    let rng = &mut thread_rng();
+
+    Groth16::<Bn254>::prove(&pk, circuit, rng).unwrap()
+}
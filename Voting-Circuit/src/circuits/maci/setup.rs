@@ -0,0 +1,35 @@
+use ark_bn254::Bn254;
+use ark_groth16::{Groth16, PreparedVerifyingKey, ProvingKey, VerifyingKey};
+use ark_std::rand::thread_rng;
+use ark_crypto_primitives::snark::SNARK;
+use crate::circuits::key_utils::KeyFiles;
+
+use super::process_circuit::ProcessCircuit;
+use super::tally_circuit::StateTallyCircuit;
+
+pub const KEY_FILES: KeyFiles = KeyFiles::new("./src/keys/maci/", "maci");
+pub const TALLY_KEY_FILES: KeyFiles = KeyFiles::new("./src/keys/maci/", "maci_tally");
+
+pub fn maci_setup(circuit: ProcessCircuit) -> (ProvingKey<Bn254>, VerifyingKey<Bn254>, PreparedVerifyingKey<Bn254>) {
+    let rng = &mut thread_rng();
+
+    let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(circuit, rng).unwrap();
+
+    let pvk = Groth16::<Bn254>::process_vk(&vk).unwrap();
+
+    KEY_FILES.store_pk_vk(pk.clone(), vk.clone());
+
+    (pk, vk, pvk)
+}
+
+pub fn maci_tally_setup(circuit: StateTallyCircuit) -> (ProvingKey<Bn254>, VerifyingKey<Bn254>, PreparedVerifyingKey<Bn254>) {
+    let rng = &mut thread_rng();
+
+    let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(circuit, rng).unwrap();
+
+    let pvk = Groth16::<Bn254>::process_vk(&vk).unwrap();
+
+    TALLY_KEY_FILES.store_pk_vk(pk.clone(), vk.clone());
+
+    (pk, vk, pvk)
+}
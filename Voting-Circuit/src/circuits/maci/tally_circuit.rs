@@ -0,0 +1,103 @@
+use ark_crypto_primitives::{crh::poseidon::constraints::CRHParametersVar, sponge::poseidon::PoseidonConfig};
+use ark_ed_on_bn254::{constraints::EdwardsVar, Fq};
+use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use crate::circuits::maci::message::{count_votes, state_commitment, StateLeaf};
+use crate::circuits::maci::process_circuit::state_commitment_gadget;
+
+type GG = EdwardsVar;
+
+#[derive(Clone, Debug)]
+pub struct StateTallyInstance {
+    pub state_cm: Option<Fq>,  // final_state_cm of the processing proof
+    pub count: Option<Vec<Fq>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StateTallyWitness {
+    pub state: Option<Vec<StateLeaf>>,
+}
+
+/// Proves the count of every vote option in the state that a `ProcessCircuit` proof ends in.
+///
+/// Only voters with a valid command, `nonce != 0`, are counted, each for the option of
+/// their last one. Options past the last count are not counted.
+#[derive(Clone, Debug)]
+pub struct StateTallyCircuit {
+    pub hash_params: PoseidonConfig<Fq>,
+    pub instance: StateTallyInstance,
+    pub witness: StateTallyWitness,
+}
+
+impl StateTallyCircuit {
+    pub fn new(
+        hash_params: PoseidonConfig<Fq>,
+        instance: StateTallyInstance,
+        witness: StateTallyWitness,
+    ) -> Self {
+        Self {
+            hash_params,
+            instance,
+            witness,
+        }
+    }
+
+    /// Counts `num_of_options` options in `state`.
+    pub fn from_state(hash_params: PoseidonConfig<Fq>, state: Vec<StateLeaf>, num_of_options: u64) -> Self {
+        let count = count_votes(&state, num_of_options);
+        let count_str = count.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        println!("count: {:?}", count_str);
+
+        let instance = StateTallyInstance {
+            state_cm: Some(state_commitment(&hash_params, &state)),
+            count: Some(count.into_iter().map(Fq::from).collect()),
+        };
+
+        let witness = StateTallyWitness {
+            state: Some(state),
+        };
+
+        Self::new(hash_params, instance, witness)
+    }
+}
+
+impl ConstraintSynthesizer<Fq> for StateTallyCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fq>) -> ark_relations::r1cs::Result<()> {
+        let hash_params = CRHParametersVar::<Fq>::new_constant(cs.clone(), self.hash_params)?;
+
+        // instance
+        let state_cm = FpVar::<Fq>::new_input(cs.clone(), || self.instance.state_cm.ok_or(SynthesisError::AssignmentMissing))?;
+        let count = Vec::<FpVar<Fq>>::new_input(cs.clone(), || self.instance.count.ok_or(SynthesisError::AssignmentMissing))?;
+
+        // witness
+        let mut state = vec![];
+        for leaf in self.witness.state.ok_or(SynthesisError::AssignmentMissing)? {
+            let pk = GG::new_witness(cs.clone(), || Ok(leaf.pk))?;
+            let vote_option = FpVar::<Fq>::new_witness(cs.clone(), || Ok(leaf.vote_option))?;
+            let nonce = FpVar::<Fq>::new_witness(cs.clone(), || Ok(leaf.nonce))?;
+            state.push((pk, vote_option, nonce));
+        }
+
+
+        // Constraints
+
+        // 1. Check state_cm = CRH(state)
+        state_commitment_gadget(&hash_params, &state)?.enforce_equal(&state_cm)?;
+
+
+        // 2. Check count_j = #{i : nonce_i != 0 and vote_option_i = j}
+        let mut has_voted = vec![];
+        for (_, _, nonce) in state.iter() {
+            has_voted.push(nonce.is_neq(&FpVar::zero())?);
+        }
+        for (j, count_j) in count.iter().enumerate() {
+            let j = FpVar::<Fq>::constant(Fq::from(j as u64));
+            let mut count_computed = FpVar::<Fq>::zero();
+            for ((_, vote_option, _), has_voted_i) in state.iter().zip(has_voted.iter()) {
+                count_computed += FpVar::from(vote_option.is_eq(&j)?.and(has_voted_i)?);
+            }
+            count_computed.enforce_equal(count_j)?;
+        }
+        Ok(())
+    }
+}
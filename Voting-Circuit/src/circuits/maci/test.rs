@@ -0,0 +1,261 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test {
+    use ark_bn254::Bn254;
+    use ark_ec::{AffineRepr, CurveGroup};
+    use ark_ed_on_bn254::EdwardsAffine;
+    use ark_ff::PrimeField;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_groth16::Groth16;
+    use ark_crypto_primitives::snark::SNARK;
+    use rand::thread_rng;
+    use crate::circuits::maci::{process_circuit::ProcessCircuit, MockingCircuit};
+    use crate::circuits::maci::message::{count_votes, initial_state, is_valid_key, process_messages, state_commitment, Command, Message};
+    use crate::circuits::maci::tally_circuit::StateTallyCircuit;
+    use crate::circuits::voting::parser::{get_g, get_user, parse_user};
+    use crate::circuits::voting::poseidon_params::get_poseidon_params;
+    use crate::circuits::maci::{
+        setup::{maci_setup, maci_tally_setup},
+        prover::{maci_prove, maci_tally_prove}
+    };
+
+    type F = ark_bn254::Fr;
+
+
+    fn make_mocking_circuit() -> ProcessCircuit {
+        let g = get_g().unwrap();
+        let voter = parse_user(&get_user(1).unwrap()).unwrap();
+        let new_sk = F::from(123456789u64);
+        let new_pk = g.mul_bigint(new_sk.into_bigint()).into_affine();
+
+        let commands = vec![
+            // voter 1 secretly changes its key
+            (voter.sk, Command { state_index: 1, new_pk, vote_option: 0, nonce: 1 }),
+            // a vote signed with the old key, e.g. in front of a briber, is ignored
+            (voter.sk, Command { state_index: 1, new_pk: voter.pk, vote_option: 2, nonce: 2 }),
+            // the real vote with the new key
+            (new_sk, Command { state_index: 1, new_pk, vote_option: 1, nonce: 2 }),
+        ];
+        make_mocking_circuit_with(commands)
+    }
+
+    fn make_mocking_circuit_with(commands: Vec<(F, Command)>) -> ProcessCircuit {
+        let tree_height = 4;        // mutable
+        let num_of_voters = 2;      // mutable
+        let coord_sk = F::from(987654321u64);
+        let g = get_g().unwrap();
+
+        <ProcessCircuit as MockingCircuit>::generate_circuit(g, coord_sk, tree_height, num_of_voters, commands).unwrap()
+    }
+
+    fn final_state(circuit: &ProcessCircuit) -> Vec<crate::circuits::maci::message::StateLeaf> {
+        let hash_params = get_poseidon_params::<F>();
+        let state = initial_state(circuit.witness.pks.as_ref().unwrap());
+        process_messages(circuit.g, &hash_params, circuit.witness.coord_sk.unwrap(), &state, circuit.instance.messages.as_ref().unwrap())
+    }
+
+    #[test]
+    fn test_maci_message() {
+        let g = get_g().unwrap();
+        let hash_params = get_poseidon_params::<F>();
+        let voter = parse_user(&get_user(0).unwrap()).unwrap();
+        let coord_sk = F::from(987654321u64);
+        let coord_pk = g.mul_bigint(coord_sk.into_bigint()).into_affine();
+        let mut rng = thread_rng();
+
+        let command = Command { state_index: 0, new_pk: voter.pk, vote_option: 1, nonce: 1 };
+        let plaintext = command.sign(g, &hash_params, voter.sk, &mut rng);
+        let message = Message::encrypt(g, &hash_params, coord_pk, &plaintext, &mut rng);
+        assert_ne!(message.data, plaintext);
+        assert_eq!(message.decrypt(g, &hash_params, coord_sk), plaintext);
+        assert_ne!(message.decrypt(g, &hash_params, coord_sk + F::from(1u64)), plaintext);
+    }
+
+    #[test]
+    fn test_maci_key_change() {
+        let test_circuit = make_mocking_circuit();
+        let state = initial_state(test_circuit.witness.pks.as_ref().unwrap());
+
+        let final_state = final_state(&test_circuit);
+        assert_eq!(final_state[0], state[0]);
+        assert_eq!(final_state[1].vote_option, F::from(1u64));
+        assert_eq!(final_state[1].nonce, F::from(2u64));
+        assert_ne!(final_state[1].pk, state[1].pk);
+    }
+
+    #[test]
+    fn test_maci_constraints() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let test_circuit = make_mocking_circuit();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_maci_garbage_message() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let mut test_circuit = make_mocking_circuit();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        // a message that decrypts to points off the curve is skipped, not unprovable
+        let mut messages = test_circuit.instance.messages.clone().unwrap();
+        messages[0].data = vec![F::from(7u64); messages[0].data.len()];
+        test_circuit.instance.messages = Some(messages.clone());
+
+        let hash_params = get_poseidon_params::<F>();
+        let final_state = final_state(&test_circuit);
+        assert_eq!(final_state, initial_state(test_circuit.witness.pks.as_ref().unwrap()));
+        test_circuit.instance.final_state_cm = Some(state_commitment(&hash_params, &final_state));
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_maci_wrong_final_state() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let mut test_circuit = make_mocking_circuit();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        // claim that the vote signed with the old key was counted
+        let hash_params = get_poseidon_params::<F>();
+        let mut state = initial_state(test_circuit.witness.pks.as_ref().unwrap());
+        state[1].vote_option = F::from(2u64);
+        state[1].nonce = F::from(2u64);
+        test_circuit.instance.final_state_cm = Some(state_commitment(&hash_params, &state));
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+
+    #[test]
+    fn test_maci_prove() {
+        let test_circuit = make_mocking_circuit();
+        let (pk, _, pvk) = maci_setup(test_circuit.clone());
+        let mut image: Vec<_> = vec![];
+
+        let coord_pk = test_circuit.instance.coord_pk.unwrap();
+        image.append(&mut vec![coord_pk.x, coord_pk.y]);
+        image.push(test_circuit.instance.voter_root.unwrap());
+        image.push(test_circuit.instance.final_state_cm.unwrap());
+
+        for message in test_circuit.instance.messages.clone().unwrap() {
+            image.append(&mut vec![message.enc_pk.x, message.enc_pk.y]);
+            image.append(&mut message.data.clone());
+        }
+
+        println!("len(image): {:?}", image.len());
+
+        let proof = maci_prove(pk, test_circuit);
+        assert!(Groth16::<Bn254>::verify_with_processed_vk(&pvk, &image, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_maci_registry() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let test_circuit = make_mocking_circuit();
+
+        // the coordinator cannot start from another voter tree
+        let mut wrong_root = test_circuit.clone();
+        wrong_root.instance.voter_root = Some(wrong_root.instance.voter_root.unwrap() + F::from(1u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+        wrong_root.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // nor drop a registered voter
+        let mut dropped = test_circuit.clone();
+        dropped.witness.pks.as_mut().unwrap().pop();
+        dropped.witness.salts.as_mut().unwrap().pop();
+        let cs = ConstraintSystem::<F>::new_ref();
+        dropped.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // nor swap in a key of its own
+        let mut swapped = test_circuit;
+        let g = swapped.g;
+        swapped.witness.pks.as_mut().unwrap()[0] = g.mul_bigint(F::from(42u64).into_bigint()).into_affine();
+        let cs = ConstraintSystem::<F>::new_ref();
+        swapped.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_maci_low_order_key() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let g = get_g().unwrap();
+        let voter = parse_user(&get_user(1).unwrap()).unwrap();
+
+        // the identity and the point of order 2 are on the curve but have no secret key
+        let identity = EdwardsAffine::zero();
+        let order_two = EdwardsAffine::new_unchecked(F::from(0u64), -F::from(1u64));
+        let low_order_key = (g.into_group() + order_two).into_affine();
+        for new_pk in [identity, order_two, low_order_key] {
+            assert!(new_pk.is_on_curve());
+            assert!(!is_valid_key(new_pk));
+
+            let mut test_circuit = make_mocking_circuit_with(vec![
+                (voter.sk, Command { state_index: 1, new_pk, vote_option: 1, nonce: 1 }),
+            ]);
+            let state = initial_state(test_circuit.witness.pks.as_ref().unwrap());
+            assert_eq!(final_state(&test_circuit), state);
+
+            // the key change is skipped, and cannot be claimed
+            let cs = ConstraintSystem::<F>::new_ref();
+            test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+            assert!(cs.is_satisfied().unwrap());
+
+            let hash_params = get_poseidon_params::<F>();
+            let mut changed = state.clone();
+            changed[1].pk = new_pk;
+            changed[1].vote_option = F::from(1u64);
+            changed[1].nonce = F::from(1u64);
+            test_circuit.instance.final_state_cm = Some(state_commitment(&hash_params, &changed));
+            let cs = ConstraintSystem::<F>::new_ref();
+            test_circuit.generate_constraints(cs.clone()).unwrap();
+            assert!(!cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_maci_tally() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let process_circuit = make_mocking_circuit();
+        let hash_params = get_poseidon_params::<F>();
+        let final_state = final_state(&process_circuit);
+        assert_eq!(count_votes(&final_state, 3), vec![0, 1, 0]);
+
+        // the tally counts the state the processing proof ends in
+        let test_circuit = StateTallyCircuit::from_state(hash_params, final_state, 3);
+        assert_eq!(test_circuit.instance.state_cm, process_circuit.instance.final_state_cm);
+
+        let cs = ConstraintSystem::<F>::new_ref();
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // voter 0 has not voted, so it does not count for option 0
+        let mut wrong_circuit = test_circuit;
+        wrong_circuit.instance.count = Some(vec![F::from(1u64), F::from(1u64), F::from(0u64)]);
+        let cs = ConstraintSystem::<F>::new_ref();
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_maci_tally_prove() {
+        let process_circuit = make_mocking_circuit();
+        let hash_params = get_poseidon_params::<F>();
+        let test_circuit = StateTallyCircuit::from_state(hash_params, final_state(&process_circuit), 3);
+        let (pk, _, pvk) = maci_tally_setup(test_circuit.clone());
+
+        let mut image = vec![process_circuit.instance.final_state_cm.unwrap()];
+        image.append(&mut test_circuit.instance.count.clone().unwrap());
+
+        let proof = maci_tally_prove(pk, test_circuit);
+        assert!(Groth16::<Bn254>::verify_with_processed_vk(&pvk, &image, &proof).unwrap());
+    }
+}
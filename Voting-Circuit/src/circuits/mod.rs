@@ -1,2 +1,5 @@
 pub mod voting;
-pub mod tally;
\ No newline at end of file
+pub mod tally;
+pub mod maci;
+pub mod registration;
+pub mod key_utils;
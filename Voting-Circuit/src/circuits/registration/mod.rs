@@ -0,0 +1,30 @@
+pub mod registration_circuit;
+pub mod test;
+pub mod prover;
+pub mod setup;
+
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::CurveGroup;
+use ark_ff::PrimeField;
+use ark_r1cs_std::groups::{CurveVar, GroupOpsBounds};
+
+use crate::Error;
+
+pub trait MockingCircuit<C, GG>
+where
+    C: CurveGroup,
+    GG: CurveVar<C, C::BaseField>,
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+    for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
+{
+    type Output;
+
+    fn generate_circuit(
+        g: C::Affine,
+        sk: C::BaseField,
+        pk: C::Affine,
+        salt: C::BaseField,
+        contract_addr: C::BaseField,
+        voting_round: u64,
+    ) -> Result<Self::Output, Error>;
+}
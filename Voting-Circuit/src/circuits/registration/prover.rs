@@ -0,0 +1,15 @@
+use ark_bn254::Bn254;
+use ark_groth16::{Groth16, ProvingKey, Proof};
+use ark_std::rand::thread_rng;
+use ark_crypto_primitives::snark::SNARK;
+
+use super::registration_circuit::RegistrationCircuit;
+
+type C = ark_ed_on_bn254::EdwardsProjective;
+type GG = ark_ed_on_bn254::constraints::EdwardsVar;
+
+pub fn registration_prove(pk: ProvingKey<Bn254>, circuit: RegistrationCircuit<C, GG>) -> Proof<Bn254> {
+    let rng = &mut thread_rng();
+
+    Groth16::<Bn254>::prove(&pk, circuit, rng).unwrap()
+}
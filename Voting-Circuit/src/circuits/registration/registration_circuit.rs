@@ -0,0 +1,152 @@
+use std::marker::PhantomData;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_crypto_primitives::{
+    crh::{poseidon::{constraints::{CRHGadget, CRHParametersVar}, CRH}, CRHScheme, CRHSchemeGadget},
+    sponge::{poseidon::PoseidonConfig, Absorb},
+};
+use crate::circuits::registration::MockingCircuit;
+use crate::circuits::voting::poseidon_params::get_poseidon_params;
+use crate::circuits::voting::registry::{identity_commitment, key_commitment_gadget};
+use crate::circuits::voting::voting_circuit::{registration_domain, ConstraintF};
+
+#[derive(Clone, Debug)]
+pub struct RegistrationInstance<C: CurveGroup> {
+    pub voting_round: Option<C::BaseField>,
+    pub nullifier: Option<C::BaseField>,  // one per key and election, so a key registers once
+    pub contract_addr: Option<C::BaseField>,  // address of the PseVoting deployment
+    pub cm: Option<C::BaseField>,  // the commitment passed to registerVoter
+}
+
+#[derive(Clone, Debug)]
+pub struct RegistrationWitness<C: CurveGroup> {
+    pub sk: Option<C::BaseField>,
+    pub pk: Option<C::Affine>,
+    pub salt: Option<C::BaseField>,
+}
+
+/// Proves that the registrant knows the secret key behind a commitment, with the same
+/// `key_commitment_gadget` as `VotingCircuit`, and publishes a nullifier of the key for the
+/// election so that the key cannot register a second commitment or replay the proof elsewhere.
+#[derive(Clone, Debug)]
+pub struct RegistrationCircuit<C: CurveGroup, GG: CurveVar<C, ConstraintF<C>>>
+where
+    C: CurveGroup,
+    GG: CurveVar<C, C::BaseField>,
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+    for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
+{
+    pub g: C::Affine,
+    pub hash_params: PoseidonConfig<C::BaseField>,
+    pub instance: RegistrationInstance<C>,
+    pub witness: RegistrationWitness<C>,
+    _curve: PhantomData<GG>,
+}
+
+impl<C, GG> RegistrationCircuit<C, GG>
+where
+    C: CurveGroup,
+    GG: CurveVar<C, C::BaseField>,
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+    for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
+{
+    pub fn new(
+        g: C::Affine,
+        hash_params: PoseidonConfig<C::BaseField>,
+        instance: RegistrationInstance<C>,
+        witness: RegistrationWitness<C>,
+    ) -> Self {
+        Self {
+            g,
+            hash_params,
+            instance,
+            witness,
+            _curve: PhantomData,
+        }
+    }
+}
+
+impl<C, GG> ConstraintSynthesizer<C::BaseField> for RegistrationCircuit<C, GG>
+where
+    C: CurveGroup,
+    GG: CurveVar<C, C::BaseField>,
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+    for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
+{
+    fn generate_constraints(self, cs: ConstraintSystemRef<C::BaseField>) -> ark_relations::r1cs::Result<()> {
+        let g = GG::new_constant(cs.clone(), self.g)?;
+        let hash_params = CRHParametersVar::<C::BaseField>::new_constant(cs.clone(), self.hash_params)?;
+
+        // instance
+        let voting_round = FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.voting_round.ok_or(SynthesisError::AssignmentMissing))?;
+        let nullifier = FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.nullifier.ok_or(SynthesisError::AssignmentMissing))?;
+        let contract_addr = FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.contract_addr.ok_or(SynthesisError::AssignmentMissing))?;
+        let cm = FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.cm.ok_or(SynthesisError::AssignmentMissing))?;
+
+        // witness
+        let sk = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.sk.ok_or(SynthesisError::AssignmentMissing))?;
+        let pk = GG::new_witness(cs.clone(), || self.witness.pk.ok_or(SynthesisError::AssignmentMissing))?;
+        let salt = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.salt.ok_or(SynthesisError::AssignmentMissing))?;
+
+
+        // Constraints
+
+        // 1-2. Check pk = g^sk, cm = CRH(pk || salt)
+        let (_, cm_computed) = key_commitment_gadget::<C, GG>(&g, &hash_params, &sk, &pk, &salt)?;
+        cm_computed.enforce_equal(&cm)?;
+
+
+        // 3. Check nullifier = CRH(sk || registration_id), registration_id = CRH(domain || contract_addr || voting_round)
+        let domain = FpVar::<C::BaseField>::constant(registration_domain());
+        let registration_id = CRHGadget::<C::BaseField>::evaluate(&hash_params, &[domain, contract_addr, voting_round])?;
+        let nullifier_computed = CRHGadget::<C::BaseField>::evaluate(&hash_params, &[sk, registration_id])?;
+        nullifier_computed.enforce_equal(&nullifier)?;
+        Ok(())
+    }
+}
+
+
+impl<C, GG> MockingCircuit<C, GG> for RegistrationCircuit<C, GG>
+where
+    C: CurveGroup,
+    GG: CurveVar<C, C::BaseField>,
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+    for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
+{
+    type Output = RegistrationCircuit<C, GG>;
+
+    fn generate_circuit(
+            g: C::Affine,
+            sk: C::BaseField,
+            pk: C::Affine,
+            salt: C::BaseField,  // fresh for every election
+            contract_addr: C::BaseField,  // address of the PseVoting deployment, see parser::from_address_to_base_field
+            voting_round: u64,
+        ) -> Result<Self::Output, crate::Error> {
+        let hash_params = get_poseidon_params::<C::BaseField>();
+
+        let (pk_x, pk_y) = pk.xy().ok_or("pk is the point at infinity")?;
+        let cm = identity_commitment(&hash_params, *pk_x, *pk_y, salt)?;
+
+        let voting_round = C::BaseField::from(voting_round);
+        let registration_id = CRH::<C::BaseField>::evaluate(&hash_params, vec![registration_domain(), contract_addr, voting_round])?;
+        let nullifier = CRH::<C::BaseField>::evaluate(&hash_params, vec![sk, registration_id])?;
+
+        let instance = RegistrationInstance {
+            voting_round: Some(voting_round),
+            nullifier: Some(nullifier),
+            contract_addr: Some(contract_addr),
+            cm: Some(cm),
+        };
+
+        let witness = RegistrationWitness {
+            sk: Some(sk),
+            pk: Some(pk),
+            salt: Some(salt),
+        };
+
+        Ok(Self::new(g, hash_params, instance, witness))
+    }
+}
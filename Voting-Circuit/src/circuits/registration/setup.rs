@@ -0,0 +1,24 @@
+use ark_bn254::Bn254;
+use ark_groth16::{Groth16, PreparedVerifyingKey, ProvingKey, VerifyingKey};
+use ark_std::rand::thread_rng;
+use ark_crypto_primitives::snark::SNARK;
+use crate::circuits::key_utils::KeyFiles;
+
+use super::registration_circuit::RegistrationCircuit;
+
+type C = ark_ed_on_bn254::EdwardsProjective;
+type GG = ark_ed_on_bn254::constraints::EdwardsVar;
+
+pub const KEY_FILES: KeyFiles = KeyFiles::new("./src/keys/registration/", "registration");
+
+pub fn registration_setup(circuit: RegistrationCircuit<C, GG>) -> (ProvingKey<Bn254>, VerifyingKey<Bn254>, PreparedVerifyingKey<Bn254>) {
+    let rng = &mut thread_rng();
+
+    let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(circuit, rng).unwrap();
+
+    let pvk = Groth16::<Bn254>::process_vk(&vk).unwrap();
+
+    KEY_FILES.store_pk_vk(pk.clone(), vk.clone());
+
+    (pk, vk, pvk)
+}
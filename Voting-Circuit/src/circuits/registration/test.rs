@@ -0,0 +1,148 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test {
+    use ark_bn254::Bn254;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use ark_groth16::Groth16;
+    use ark_crypto_primitives::snark::SNARK;
+    use crate::circuits::registration::{registration_circuit::RegistrationCircuit, MockingCircuit};
+    use crate::circuits::voting::parser::{from_address_to_base_field, get_g, get_user, parse_user};
+    use crate::circuits::registration::{
+        setup::registration_setup,
+        prover::registration_prove
+    };
+
+    type C = ark_ed_on_bn254::EdwardsProjective;
+    type GG = ark_ed_on_bn254::constraints::EdwardsVar;
+
+    type F = ark_bn254::Fr;
+
+    const CONTRACT_ADDR: &str = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
+
+
+    fn make_mocking_circuit(voter_pos: usize, salt: u64) -> RegistrationCircuit<C, GG> {
+        make_mocking_circuit_at(CONTRACT_ADDR, 1, voter_pos, salt)
+    }
+
+    fn make_mocking_circuit_at(contract: &str, voting_round: u64, voter_pos: usize, salt: u64) -> RegistrationCircuit<C, GG> {
+        let parsed_user = parse_user(&get_user(voter_pos).unwrap()).unwrap();
+        let contract_addr = from_address_to_base_field(contract).unwrap();
+        <RegistrationCircuit<C, GG> as MockingCircuit<C, GG>>::generate_circuit(
+            get_g().unwrap(), parsed_user.sk, parsed_user.pk, F::from(salt), contract_addr, voting_round
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_registration_constraints() {
+        let test_circuit = make_mocking_circuit(1, 7);
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_registration_wrong_sk() {
+        // registering the credential of voter 1 without its secret key
+        let mut test_circuit = make_mocking_circuit(1, 7);
+        test_circuit.witness.sk = Some(parse_user(&get_user(2).unwrap()).unwrap().sk);
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // or with a key of one's own
+        let mut test_circuit = make_mocking_circuit(1, 7);
+        test_circuit.witness = make_mocking_circuit(2, 7).witness;
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_registration_nullifier() {
+        use crate::circuits::voting::{voting_circuit::VotingCircuit, ElectionConfig};
+        use crate::circuits::voting::ballot::{Ballot, BallotMode, Contest};
+        use crate::circuits::voting::parser::get_h;
+        let nullifier = make_mocking_circuit(1, 7).instance.nullifier.unwrap();
+
+        // a second commitment of the same key in the same election has the same nullifier
+        assert_eq!(make_mocking_circuit(1, 8).instance.nullifier.unwrap(), nullifier);
+        assert_ne!(make_mocking_circuit(2, 7).instance.nullifier.unwrap(), nullifier);
+
+        // unlike its registrations in other rounds and deployments
+        assert_ne!(make_mocking_circuit_at(CONTRACT_ADDR, 2, 1, 7).instance.nullifier.unwrap(), nullifier);
+        assert_ne!(make_mocking_circuit_at("0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512", 1, 1, 7).instance.nullifier.unwrap(), nullifier);
+
+        // and it does not reveal which ballot the voter casts
+        let user = parse_user(&get_user(1).unwrap()).unwrap();
+        let contests = vec![Contest { ballot_mode: BallotMode::Plurality, ballot_bound: None, num_of_candidates: 2, candidate_limit: 2 }];
+        let config = ElectionConfig { tree_height: 4, contract_addr: from_address_to_base_field(CONTRACT_ADDR).unwrap(), ..Default::default() };
+        let voting_circuit = <VotingCircuit<C, GG> as crate::circuits::voting::MockingCircuit<C, GG>>::generate_circuit(
+            get_g().unwrap(), get_h().unwrap(), user.sk, user.pk, contests, vec![Ballot::Single(0)], config
+        ).unwrap();
+        assert_ne!(voting_circuit.instance.sn.unwrap(), nullifier);
+
+        // a wrong nullifier does not verify
+        let mut test_circuit = make_mocking_circuit(1, 7);
+        test_circuit.instance.nullifier = Some(nullifier + F::from(1u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    const CONTRACT_FIXTURE: &str = "../Voting-Contract/test/fixtures/registration_inputs.json";
+
+    /// Checks the inputs the PseVoting tests in Voting-Contract take against real registration
+    /// circuits, and rewrites them with UPDATE_CONTRACT_FIXTURE set: two commitments of voter 1
+    /// and one of voter 2, all for the first deployment of the default hardhat account.
+    #[test]
+    fn test_registration_contract_inputs() {
+        let registrations = [(1, 7), (1, 8), (2, 7)].iter().map(|(voter_pos, salt)| {
+            let test_circuit = make_mocking_circuit(*voter_pos, *salt);
+            let instance = test_circuit.instance.clone();
+            let cs = ConstraintSystem::<F>::new_ref();
+
+            test_circuit.generate_constraints(cs.clone()).unwrap();
+            assert!(cs.is_satisfied().unwrap());
+            let inputs = [instance.voting_round, instance.nullifier, instance.contract_addr, instance.cm];
+            serde_json::json!({
+                "contract": CONTRACT_ADDR,
+                "votingRound": instance.voting_round.unwrap().to_string(),
+                "cm": instance.cm.unwrap().to_string(),
+                "inputs": inputs.iter().map(|x| x.unwrap().to_string()).collect::<Vec<String>>(),
+            })
+        }).collect::<Vec<_>>();
+
+        let fixture = serde_json::Value::Array(registrations);
+        if std::env::var("UPDATE_CONTRACT_FIXTURE").is_ok() {
+            std::fs::write(CONTRACT_FIXTURE, serde_json::to_string_pretty(&fixture).unwrap() + "\n").unwrap();
+        }
+
+        let committed: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(CONTRACT_FIXTURE).unwrap()).unwrap();
+        assert_eq!(committed, fixture);
+    }
+
+    #[test]
+    fn test_registration_prove() {
+        let test_circuit = make_mocking_circuit(1, 7);
+        let (pk, _, pvk) = registration_setup(test_circuit.clone());
+
+        let instance = &test_circuit.instance;
+        let image = vec![instance.voting_round.unwrap(), instance.nullifier.unwrap(), instance.contract_addr.unwrap(), instance.cm.unwrap()];
+        let proof = registration_prove(pk, test_circuit.clone());
+        assert!(Groth16::<Bn254>::verify_with_processed_vk(&pvk, &image, &proof).unwrap());
+
+        let mut other_image = image.clone();
+        other_image[3] = make_mocking_circuit(1, 8).instance.cm.unwrap();
+        assert!(!Groth16::<Bn254>::verify_with_processed_vk(&pvk, &other_image, &proof).unwrap());
+
+        // nor is the proof valid for another deployment
+        let mut other_image = image;
+        other_image[2] = from_address_to_base_field("0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512").unwrap();
+        assert!(!Groth16::<Bn254>::verify_with_processed_vk(&pvk, &other_image, &proof).unwrap());
+    }
+}
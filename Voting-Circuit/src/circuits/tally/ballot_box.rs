@@ -0,0 +1,64 @@
+use std::collections::{HashMap, HashSet};
+use ark_ff::PrimeField;
+
+use crate::Error;
+
+/// Keeps the latest ballot of every voter for the tally.
+///
+/// Ballots are keyed by their serial number `sn`. A re-vote names the ballot it replaces
+/// through `prev_sn`, and a zero `prev_sn` starts a new chain (and is what ballots without
+/// re-voting use). The link is public: whoever knows the `sn` of a ballot sees when it is
+/// replaced. Re-voting only lets a voter change their ballot; `maci` is the mode whose
+/// overrides stay private.
+#[derive(Clone, Debug)]
+pub struct BallotBox<F: PrimeField, B> {
+    latest: HashMap<F, B>,
+    replaced: HashSet<F>,
+}
+
+impl<F: PrimeField, B> Default for BallotBox<F, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField, B> BallotBox<F, B> {
+    pub fn new() -> Self {
+        Self {
+            latest: HashMap::new(),
+            replaced: HashSet::new(),
+        }
+    }
+
+    /// Accepts a verified ballot, replacing the one with serial number `prev_sn`.
+    pub fn submit(&mut self, sn: F, prev_sn: F, ballot: B) -> Result<(), Error> {
+        if sn.is_zero() {
+            return Err("serial number must not be zero".into());
+        }
+        if self.latest.contains_key(&sn) || self.replaced.contains(&sn) {
+            return Err("serial number is already used".into());
+        }
+        if !prev_sn.is_zero() {
+            // a chain can only be extended from its latest ballot
+            if self.latest.remove(&prev_sn).is_none() {
+                return Err("previous ballot is unknown or already replaced".into());
+            }
+            self.replaced.insert(prev_sn);
+        }
+        self.latest.insert(sn, ballot);
+        Ok(())
+    }
+
+    /// The latest ballot of every voter, in no particular order.
+    pub fn ballots(&self) -> impl Iterator<Item = &B> {
+        self.latest.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.latest.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_empty()
+    }
+}
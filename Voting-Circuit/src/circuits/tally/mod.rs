@@ -1 +1,30 @@
-pub mod tally_circuit;
\ No newline at end of file
+pub mod tally_circuit;
+pub mod ballot_box;
+pub mod test;
+pub mod prover;
+pub mod setup;
+
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::CurveGroup;
+use ark_ff::PrimeField;
+use ark_r1cs_std::groups::{CurveVar, GroupOpsBounds};
+
+use crate::Error;
+
+pub trait MockingCircuit<C, GG>
+where
+    C: CurveGroup,
+    GG: CurveVar<C, C::BaseField>,
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+    for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
+{
+    type F;
+    type Output;
+
+    fn generate_circuit(
+        g: C::Affine,
+        x: C::BaseField,
+        aggregate_votes: Vec<(C::Affine, C::Affine)>,
+        max_count: u64,
+    ) -> Result<Self::Output, Error>;
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{Field, PrimeField};
+use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_crypto_primitives::sponge::Absorb;
+use crate::circuits::tally::MockingCircuit;
+use crate::circuits::voting::gadgets::to_bits_le_in_range;
+
+pub type ConstraintF<C> = <<C as CurveGroup>::BaseField as Field>::BasePrimeField;
+
+/// Bit width of every count. `g^count` only fixes count modulo the order of `g`, so
+/// counts are range checked to rule out `count + k * order`.
+pub const COUNT_BITS: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct TallyInstance<C: CurveGroup> {
+    pub h: Option<C::Affine>,
+    pub g_r: Option<Vec<C::Affine>>,
+    pub g_mh_r: Option<Vec<C::Affine>>,
+    pub count: Option<Vec<C::BaseField>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TallyWitness<C: CurveGroup> {
+    pub x: Option<C::BaseField>,
+}
+
+
+#[derive(Clone, Debug)]
+pub struct TallyCircuit<C: CurveGroup, GG: CurveVar<C, ConstraintF<C>>>
+where
+    C: CurveGroup,
+    GG: CurveVar<C, C::BaseField>,
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+    for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
+{
+    pub g: C::Affine,
+    pub instance: TallyInstance<C>,
+    pub witness: TallyWitness<C>,
+    _curve: PhantomData<GG>,
+}
+
+impl<C, GG> TallyCircuit<C, GG>
+where
+    C: CurveGroup,
+    GG: CurveVar<C, C::BaseField>,
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+    for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
+{
+    pub fn new(
+        g: C::Affine,
+        instance: TallyInstance<C>,
+        witness: TallyWitness<C>,
+    ) -> Self {
+        Self {
+            g,
+            instance,
+            witness,
+            _curve: PhantomData,
+        }
+    }
+}
+
+
+
+impl<C, GG> ConstraintSynthesizer<C::BaseField> for TallyCircuit<C, GG>
+where
+    C: CurveGroup,
+    GG: CurveVar<C, C::BaseField>,
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+    for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
+{
+    fn generate_constraints(self, cs: ConstraintSystemRef<C::BaseField>) -> ark_relations::r1cs::Result<()> {
+        let g = GG::new_constant(cs.clone(), self.g)?;
+
+        // instance
+        let h = GG::new_input(cs.clone(), || self.instance.h.ok_or(SynthesisError::AssignmentMissing))?;
+        let g_r = Vec::<GG>::new_input(cs.clone(), || self.instance.g_r.ok_or(SynthesisError::AssignmentMissing))?;
+        let g_mh_r = Vec::<GG>::new_input(cs.clone(), || self.instance.g_mh_r.ok_or(SynthesisError::AssignmentMissing))?;
+        let count = Vec::<FpVar<C::BaseField>>::new_input(cs.clone(), || self.instance.count.ok_or(SynthesisError::AssignmentMissing))?;
+
+        // witness
+        let x = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.x.ok_or(SynthesisError::AssignmentMissing))?;
+
+        // one count for every aggregated ballot
+        if g_mh_r.len() != g_r.len() || count.len() != g_r.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+
+        // Constraints
+
+        // 1. Check h = g^x
+        let x_bits = x.to_bits_le()?;
+        let h_computed = g.scalar_mul_le(x_bits.iter())?;
+        h_computed.enforce_equal(&h)?;
+
+
+        // 2. Check count < 2^64, g^count = g^mh^r / (g^r)^x
+        for (i, count_i) in count.iter().enumerate() {
+            let g_count = g.scalar_mul_le(to_bits_le_in_range(count_i, COUNT_BITS)?.iter())?;
+            let g_count_computed = g_mh_r[i].clone() - g_r[i].scalar_mul_le(x_bits.iter())?;
+            g_count.enforce_equal(&g_count_computed)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Recovers `m <= max_count` from `g^m` with baby-step giant-step, so that counts
+/// of weighted ballots (more than one point per ballot) stay cheap to decrypt.
+pub fn decrypt_count<C: CurveGroup>(
+    g: C::Affine,
+    x: C::BaseField,
+    g_r: C::Affine,
+    g_mh_r: C::Affine,
+    max_count: u64,
+) -> Option<u64>
+where
+    C::BaseField: PrimeField,
+{
+    let g_m = g_mh_r.into_group() - g_r.mul_bigint(x.into_bigint());
+
+    // baby steps: g^j for j < step
+    let step = ((max_count + 1) as f64).sqrt().ceil() as u64;
+    let mut baby_steps = HashMap::new();
+    let mut acc = C::zero();
+    for j in 0..step {
+        baby_steps.entry(acc.into_affine()).or_insert(j);
+        acc += g;
+    }
+
+    // giant steps: g^m / g^(i * step)
+    let giant_step = acc;
+    let mut acc = g_m;
+    for i in 0..=step {
+        if let Some(j) = baby_steps.get(&acc.into_affine()) {
+            let m = i * step + j;
+            return (m <= max_count).then_some(m);
+        }
+        acc -= giant_step;
+    }
+    None
+}
+
+
+impl<C: CurveGroup, GG: CurveVar<C, C::BaseField>> MockingCircuit<C, GG> for TallyCircuit<C, GG>
+where
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+    for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
+{
+    type F = C::BaseField;
+    type Output = TallyCircuit<C, GG>;
+
+    fn generate_circuit(
+            g: C::Affine,
+            x: C::BaseField,
+            aggregate_votes: Vec<(C::Affine, C::Affine)>,  // (g^r, g^mh^r) of every candidate, summed over the ballots
+            max_count: u64,
+        ) -> Result<Self::Output, crate::Error> {
+        // h = g^x
+        let h = g.mul_bigint(x.into_bigint()).into_affine();
+
+        // count
+        let mut count = vec![];
+        for (g_r_i, g_mh_r_i) in aggregate_votes.iter() {
+            let count_i = decrypt_count::<C>(g, x, *g_r_i, *g_mh_r_i, max_count).ok_or("failed to decrypt the aggregated vote")?;
+            count.push(Self::F::from(count_i));
+        }
+        let (g_r, g_mh_r) = aggregate_votes.into_iter().unzip();
+
+        let count_str = count.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        println!("count: {:?}", count_str);
+
+        let instance = TallyInstance {
+            h: Some(h),
+            g_r: Some(g_r),
+            g_mh_r: Some(g_mh_r),
+            count: Some(count),
+        };
+
+        let witness = TallyWitness {
+            x: Some(x),
+        };
+
+        Ok(Self::new(g, instance, witness))
+    }
+}
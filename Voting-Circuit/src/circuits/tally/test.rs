@@ -0,0 +1,226 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test {
+    use ark_bn254::Bn254;
+    use ark_ff::Field;
+    use crate::circuits::tally::{tally_circuit::TallyCircuit, MockingCircuit};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_groth16::Groth16;
+    use ark_crypto_primitives::snark::SNARK;
+    use crate::circuits::voting::parser::{get_g, get_h, get_user, get_x, parse_user};
+    use crate::circuits::voting::ballot::{Ballot, BallotMode, Contest};
+    use crate::circuits::tally::{
+        setup::{tally_setup, KEY_FILES},
+        prover::tally_prove
+    };
+
+    type C = ark_ed_on_bn254::EdwardsProjective;
+    type EdwardsAffine = ark_ed_on_bn254::EdwardsAffine;
+    type GG = ark_ed_on_bn254::constraints::EdwardsVar;
+
+    type F = ark_bn254::Fr;
+
+
+    fn make_mocking_circuit() -> TallyCircuit<C, GG> {
+        let contest = Contest { ballot_mode: BallotMode::Plurality, ballot_bound: None, num_of_candidates: 2, candidate_limit: 2 };
+        let ballots = vec![         // mutable
+            Ballot::Single(1),
+            Ballot::Single(0),
+            Ballot::Single(1),
+        ];
+
+        make_mocking_circuit_with(contest, ballots, 3)
+    }
+
+    fn make_mocking_circuit_with(contest: Contest, ballots: Vec<Ballot>, max_count: u64) -> TallyCircuit<C, GG> {
+        let g = get_g().unwrap();
+        let x = get_x().unwrap();
+
+        <TallyCircuit<C, GG> as MockingCircuit<C, GG>>::generate_circuit(
+            g, x, aggregate_votes(contest, ballots), max_count
+        ).unwrap()
+    }
+
+    /// The (g_r, vote_cm) of every candidate summed over the voting circuits of the ballots,
+    /// as `PseVoting` aggregates them. Ballot i is cast by voter i.
+    fn aggregate_votes(contest: Contest, ballots: Vec<Ballot>) -> Vec<(EdwardsAffine, EdwardsAffine)> {
+        use ark_ec::CurveGroup;
+        use crate::circuits::voting::{voting_circuit::VotingCircuit, ElectionConfig, MockingCircuit};
+        let (g, h) = (get_g().unwrap(), get_h().unwrap());
+        let num_of_voters = ballots.len() as u64;
+
+        let mut g_r = vec![C::default(); contest.candidate_limit as usize];
+        let mut vote_cm = vec![C::default(); contest.candidate_limit as usize];
+        for (voter_pos, ballot) in ballots.into_iter().enumerate() {
+            let user = parse_user(&get_user(voter_pos).unwrap()).unwrap();
+            let config = ElectionConfig { num_of_voters, voter_pos: voter_pos as u64, ..Default::default() };
+            let voting_circuit = <VotingCircuit<C, GG> as MockingCircuit<C, GG>>::generate_circuit(g, h, user.sk, user.pk, vec![contest], vec![ballot], config).unwrap();
+
+            let instance = &voting_circuit.instance.contests[0];
+            for (i, (g_r_i, vote_cm_i)) in instance.g_r.as_ref().unwrap().iter().zip(instance.vote_cm.as_ref().unwrap()).enumerate() {
+                g_r[i] += g_r_i;
+                vote_cm[i] += vote_cm_i;
+            }
+        }
+        C::normalize_batch(&g_r).into_iter().zip(C::normalize_batch(&vote_cm)).collect()
+    }
+
+    #[test]
+    fn test_tally_constraints() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let test_circuit = make_mocking_circuit();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_tally_cumulative() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let contest = Contest { ballot_mode: BallotMode::Cumulative { bits: 4 }, ballot_bound: Some(5), num_of_candidates: 3, candidate_limit: 3 };
+        let ballots = vec![
+            Ballot::Points(vec![3, 0, 2]),
+            Ballot::Points(vec![0, 5, 0]),
+            Ballot::Points(vec![4, 1, 0]),
+        ];
+        let test_circuit = make_mocking_circuit_with(contest, ballots, 15);
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        let count = test_circuit.instance.count.clone().unwrap();
+        assert_eq!(count, vec![F::from(7u64), F::from(6u64), F::from(2u64)]);
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_decrypt_count() {
+        use ark_ec::{AffineRepr, CurveGroup};
+        use ark_ff::PrimeField;
+        use crate::circuits::tally::tally_circuit::decrypt_count;
+        let g = get_g().unwrap();
+        let x = get_x().unwrap();
+        let h = g.mul_bigint(x.into_bigint()).into_affine();
+        let r = F::from(12345u64);
+        let g_r = g.mul_bigint(r.into_bigint()).into_affine();
+
+        for m in [0u64, 1, 99, 100, 1000] {
+            let g_mh_r = (g.mul_bigint([m]) + h.mul_bigint(r.into_bigint())).into_affine();
+            assert_eq!(decrypt_count::<C>(g, x, g_r, g_mh_r, 1000), Some(m));
+        }
+        let g_mh_r = (g.mul_bigint([1001u64]) + h.mul_bigint(r.into_bigint())).into_affine();
+        assert_eq!(decrypt_count::<C>(g, x, g_r, g_mh_r, 1000), None);
+    }
+
+    #[test]
+    fn test_tally_wrong_count() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let mut test_circuit = make_mocking_circuit();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        let mut count = test_circuit.instance.count.clone().unwrap();
+        count[0] += F::ONE;
+        test_circuit.instance.count = Some(count);
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_tally_count_length() {
+        use ark_relations::r1cs::{ConstraintSynthesizer, SynthesisError};
+        let mut test_circuit = make_mocking_circuit();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        // a count for a candidate without an aggregated ballot
+        let mut count = test_circuit.instance.count.clone().unwrap();
+        count.push(F::ONE);
+        test_circuit.instance.count = Some(count);
+
+        assert!(matches!(test_circuit.generate_constraints(cs), Err(SynthesisError::Unsatisfiable)));
+    }
+
+    #[test]
+    fn test_tally_aliased_count() {
+        use ark_ff::PrimeField;
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let mut test_circuit = make_mocking_circuit();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        // count + the order of g decrypts to the same g^count
+        let order = F::from_bigint(ark_ed_on_bn254::Fr::MODULUS).unwrap();
+        let mut count = test_circuit.instance.count.clone().unwrap();
+        count[1] += order;
+        test_circuit.instance.count = Some(count);
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+
+    #[test]
+    fn test_ballot_box() {
+        use crate::circuits::tally::ballot_box::BallotBox;
+        let zero = F::from(0u64);
+        let (sn_a0, sn_a1, sn_a2, sn_b0) = (F::from(10u64), F::from(11u64), F::from(12u64), F::from(20u64));
+        let mut ballot_box = BallotBox::new();
+
+        ballot_box.submit(sn_a0, zero, Ballot::Single(0)).unwrap();
+        ballot_box.submit(sn_b0, zero, Ballot::Single(1)).unwrap();
+        ballot_box.submit(sn_a1, sn_a0, Ballot::Single(1)).unwrap();
+
+        // a used serial number, a fork of a replaced ballot and an unknown previous ballot
+        assert!(ballot_box.submit(sn_b0, zero, Ballot::Single(0)).is_err());
+        assert!(ballot_box.submit(sn_a2, sn_a0, Ballot::Single(0)).is_err());
+        assert!(ballot_box.submit(sn_a2, F::from(99u64), Ballot::Single(0)).is_err());
+        assert!(ballot_box.submit(sn_a0, zero, Ballot::Single(0)).is_err());
+
+        assert_eq!(ballot_box.len(), 2);
+        let ballots = ballot_box.ballots().cloned().collect::<Vec<_>>();
+        assert!(ballots.iter().all(|ballot| *ballot == Ballot::Single(1)));
+
+        let contest = Contest { ballot_mode: BallotMode::Plurality, ballot_bound: None, num_of_candidates: 2, candidate_limit: 2 };
+        let test_circuit = make_mocking_circuit_with(contest, ballots, 2);
+        assert_eq!(test_circuit.instance.count.unwrap(), vec![F::from(0u64), F::from(2u64)]);
+    }
+
+
+    #[test]
+    fn test_tally_setup() {
+        let test_circuit = make_mocking_circuit();
+        tally_setup(test_circuit);
+
+        // the shared key helpers keep the file names of every circuit
+        assert_eq!(KEY_FILES.pk_file(), "./src/keys/tally/tally.pk.dat");
+        assert_eq!(KEY_FILES.vk_uncompressed_file(), "./src/keys/tally/tally.vk.uncompressed.dat");
+        assert!(std::path::Path::new(&KEY_FILES.pk_file()).exists());
+    }
+
+    #[test]
+    fn test_tally_prove() {
+        let test_circuit = make_mocking_circuit();
+        let (pk, _, pvk) = tally_setup(test_circuit.clone());
+        let mut image: Vec<_> = vec![];
+
+        let h = test_circuit.instance.h.unwrap();
+        image.append(&mut vec![h.x, h.y]);
+
+        for i in test_circuit.instance.g_r.clone().unwrap() {
+            image.append(&mut vec![i.x, i.y]);
+        }
+
+        for i in test_circuit.instance.g_mh_r.clone().unwrap() {
+            image.append(&mut vec![i.x, i.y]);
+        }
+
+        image.append(&mut test_circuit.instance.count.clone().unwrap());
+
+        println!("len(image): {:?}", image.len());
+
+        let proof = tally_prove(pk, test_circuit);
+        println!("proof: {:?}", proof);
+        assert!(Groth16::<Bn254>::verify_with_processed_vk(&pvk, &image, &proof).unwrap());
+    }
+}
@@ -0,0 +1,295 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
+use ark_relations::r1cs::SynthesisError;
+
+use crate::circuits::voting::gadgets::{enforce_in_range, enforce_less_or_equal};
+use crate::Error;
+
+/// Bit width of the public `ballot_bound` that sums of `vote_m` are compared against.
+pub const BALLOT_BOUND_BITS: usize = 64;
+
+/// Largest `bits` of the modes that spread points. Squares of the points then stay below
+/// `2^64`, so neither their sum nor the sum of the points can wrap around the field.
+pub const MAX_POINT_BITS: usize = 32;
+
+/// Validity rule for `vote_m`. It changes the circuit, so it is fixed when the keys are generated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BallotMode {
+    /// Exactly one candidate is chosen.
+    Plurality,
+    /// Exactly one candidate is chosen, given as a single `choice` index that the circuit
+    /// decomposes into a one-hot `vote_m`.
+    Indexed,
+    /// At most one candidate is chosen. An all-zero `vote_m` is an abstention.
+    Abstainable,
+    /// Any number of candidates up to the public `ballot_bound` (k) are approved.
+    Approval,
+    /// A budget of points equal to the public `ballot_bound` is spread across candidates,
+    /// with less than `2^bits` points each.
+    Cumulative { bits: usize },
+    /// Less than `2^bits` votes per candidate, with the sum of their squares at most
+    /// the public `ballot_bound` (credits).
+    Quadratic { bits: usize },
+    /// Points equal to the private weight in the voter's `[addr, weight]` leaf are spread
+    /// across candidates, with less than `2^bits` points each.
+    Weighted { bits: usize },
+    /// A (partial) ranking of the candidates. The k ranked candidates hold the distinct scores
+    /// `num_of_candidates - k + 1..=num_of_candidates`, the most preferred one the highest, and
+    /// the others zero, so that the homomorphic sum of the scores is the Borda count.
+    Ranked,
+}
+
+/// A question of the election, with its own candidates and validity rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Contest {
+    pub ballot_mode: BallotMode,
+    pub ballot_bound: Option<u64>,  // k of approval, budget of cumulative, credits of quadratic voting
+    pub num_of_candidates: u64,
+    pub candidate_limit: u64,
+}
+
+/// The choice of a voter, before it is encoded into `vote_m`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ballot {
+    /// A vote for the candidate at this index.
+    Single(u64),
+    /// A blank ballot that only counts toward turnout.
+    Abstain,
+    /// Approval of every candidate at these indices.
+    Approval(Vec<u64>),
+    /// Points (or quadratic votes) given to each candidate, in candidate order.
+    Points(Vec<u64>),
+    /// Candidate indices from the most to the least preferred one.
+    Ranking(Vec<u64>),
+}
+
+impl BallotMode {
+    /// Whether the mode takes `ballot_bound` as a public input.
+    pub fn has_bound(&self) -> bool {
+        matches!(self, BallotMode::Approval | BallotMode::Cumulative { .. } | BallotMode::Quadratic { .. })
+    }
+
+    /// Whether the voter tree stores `[addr, weight]` leaves.
+    pub fn is_weighted(&self) -> bool {
+        matches!(self, BallotMode::Weighted { .. })
+    }
+
+    /// Bit width of every point, for the modes that spread points across candidates.
+    pub fn point_bits(&self) -> Option<usize> {
+        match self {
+            BallotMode::Cumulative { bits } | BallotMode::Quadratic { bits } | BallotMode::Weighted { bits } => Some(*bits),
+            _ => None,
+        }
+    }
+
+    /// Whether `vote_m` is derived in the circuit from a single `choice` witness.
+    pub fn is_indexed(&self) -> bool {
+        matches!(self, BallotMode::Indexed)
+    }
+
+    /// Decomposes `choice` into `candidate_limit` boolean slots with `Sum(i * vote_m[i]) == choice`.
+    /// Together with `Sum(vote_m) == 1` from `enforce_valid`, `vote_m` is the one-hot vector of
+    /// `choice`, and no `vote_m` exists for a `choice` out of range.
+    ///
+    /// A boolean slot costs one constraint where the `vote_m[i]^2 - vote_m[i] == 0` check of
+    /// plurality mode costs two, so an indexed ballot saves `candidate_limit - 1` constraints.
+    pub fn one_hot<F: PrimeField>(choice: &FpVar<F>, candidate_limit: usize) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let cs = choice.cs();
+        let mut vote_m = vec![];
+        let mut index_sum = FpVar::<F>::zero();
+        for i in 0..candidate_limit {
+            let i = F::from(i as u64);
+            let vote_m_i = FpVar::from(Boolean::new_witness(cs.clone(), || choice.value().map(|choice| choice == i))?);
+            index_sum += &vote_m_i * i;
+            vote_m.push(vote_m_i);
+        }
+        index_sum.enforce_equal(choice)?;
+        Ok(vote_m)
+    }
+
+    /// `ballot_bound` is the public bound of the mode, or the voter's weight in weighted mode.
+    pub fn enforce_valid<F: PrimeField>(
+        &self,
+        vote_m: &[FpVar<F>],
+        num_of_candidates: &FpVar<F>,
+        ballot_bound: Option<&FpVar<F>>,
+    ) -> Result<(), SynthesisError> {
+        let zero = FpVar::<F>::zero();
+        let one = FpVar::<F>::one();
+        if self.point_bits().is_some_and(|bits| bits > MAX_POINT_BITS) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        // 5. Sum(vote_m) == 1 (or 0 when abstaining is allowed, <= k when approving, == budget when cumulating,
+        //    Sum(vote_m^2) <= credits for quadratic votes, == weight when weighted)
+        let sum_vote_m = vote_m.iter().fold(FpVar::zero(), |acc, vote| acc + vote);
+        match self {
+            BallotMode::Plurality => sum_vote_m.enforce_equal(&one)?,
+            // the slots of a one-hot vote_m are boolean by construction
+            BallotMode::Indexed => return sum_vote_m.enforce_equal(&one),
+            BallotMode::Abstainable => (&sum_vote_m * (&sum_vote_m - &one)).enforce_equal(&zero)?,
+            BallotMode::Approval => {
+                let k = ballot_bound.ok_or(SynthesisError::AssignmentMissing)?;
+                enforce_less_or_equal(&sum_vote_m, k, BALLOT_BOUND_BITS)?;
+            }
+            BallotMode::Cumulative { .. } | BallotMode::Weighted { .. } => {
+                let budget = ballot_bound.ok_or(SynthesisError::AssignmentMissing)?;
+                sum_vote_m.enforce_equal(budget)?;
+            }
+            BallotMode::Quadratic { .. } => {
+                let credits = ballot_bound.ok_or(SynthesisError::AssignmentMissing)?;
+                let sum_vote_m_sq = vote_m.iter().fold(FpVar::zero(), |acc, vote| acc + vote * vote);
+                enforce_less_or_equal(&sum_vote_m_sq, credits, BALLOT_BOUND_BITS)?;
+            }
+            BallotMode::Ranked => return Self::enforce_valid_ranking(vote_m, num_of_candidates),
+        }
+
+
+        // 6. vote_m[i] == 0 or 1 (or < 2^bits when spreading points)
+        for i in vote_m.iter() {
+            if let Some(bits) = self.point_bits() {
+                enforce_in_range(i, bits)?;
+                continue;
+            }
+            let vote_m_i_sq = i * i;
+            let vote_m_sq_minus_vote_m = vote_m_i_sq - i;
+            vote_m_sq_minus_vote_m.enforce_equal(&zero)?;
+        }
+        Ok(())
+    }
+
+    fn enforce_valid_ranking<F: PrimeField>(vote_m: &[FpVar<F>], num_of_candidates: &FpVar<F>) -> Result<(), SynthesisError> {
+        let zero = FpVar::<F>::zero();
+        let bits = (usize::BITS - vote_m.len().leading_zeros()) as usize;
+
+        // 5. vote_m[i] <= num_of_candidates
+        for i in vote_m.iter() {
+            enforce_in_range(i, bits)?;
+            enforce_less_or_equal(i, num_of_candidates, bits)?;
+        }
+
+
+        // 6. vote_m[i] != vote_m[j] unless both are unranked
+        let mut num_ranked = FpVar::<F>::zero();
+        for (i, vote_m_i) in vote_m.iter().enumerate() {
+            let is_unranked = vote_m_i.is_eq(&zero)?;
+            for vote_m_j in vote_m.iter().skip(i + 1) {
+                let is_same = vote_m_i.is_eq(vote_m_j)?;
+                is_same.and(&is_unranked.not())?.enforce_equal(&Boolean::Constant(false))?;
+            }
+            num_ranked += FpVar::from(is_unranked.not());
+        }
+
+
+        // 6. Sum(vote_m) == k * n - k * (k - 1) / 2 for k ranked candidates out of n, the largest
+        //    sum of k distinct scores in 1..=n, which only the top k scores reach. A gap such as
+        //    {5, 1} would push a rival down without ranking anyone above them.
+        let sum_vote_m = vote_m.iter().fold(FpVar::zero(), |acc, vote| acc + vote);
+        let top_k_sum_doubled = &num_ranked * (num_of_candidates.double()? - &num_ranked + FpVar::one());
+        top_k_sum_doubled.enforce_equal(&sum_vote_m.double()?)
+    }
+}
+
+impl Ballot {
+    /// Encodes the ballot into `vote_m`, padded with zeros up to `candidate_limit`.
+    /// `ballot_bound` is the public bound of the mode, or the voter's weight in weighted mode.
+    pub fn to_vote_m<F: PrimeField>(
+        &self,
+        ballot_mode: BallotMode,
+        ballot_bound: Option<u64>,
+        num_of_candidates: u64,
+        candidate_limit: u64,
+    ) -> Result<Vec<F>, Error> {
+        if num_of_candidates > candidate_limit {
+            return Err("num_of_candidates exceeds candidate_limit".into());
+        }
+        if (ballot_mode.has_bound() || ballot_mode.is_weighted()) != ballot_bound.is_some() {
+            return Err("ballot_bound must be given exactly for the ballot modes that take it".into());
+        }
+        if let Some(bits) = ballot_mode.point_bits().filter(|bits| *bits > MAX_POINT_BITS) {
+            return Err(format!("points of {} bits exceed the maximum of {}", bits, MAX_POINT_BITS).into());
+        }
+
+        let mut vote_m = vec![F::zero(); candidate_limit as usize];
+        match (self, ballot_mode) {
+            (Ballot::Single(vote_index), BallotMode::Plurality | BallotMode::Abstainable | BallotMode::Indexed) => {
+                if *vote_index >= num_of_candidates {
+                    return Err(format!("vote_index {} is out of range for {} candidates", vote_index, num_of_candidates).into());
+                }
+                vote_m[*vote_index as usize] = F::one();
+            }
+            (Ballot::Abstain, BallotMode::Abstainable) => {}
+            (Ballot::Abstain, _) => {
+                return Err("abstaining is not allowed in this ballot mode".into());
+            }
+            (Ballot::Approval(indices), BallotMode::Approval) => {
+                let k = ballot_bound.unwrap_or_default();
+                if indices.len() as u64 > k {
+                    return Err(format!("{} approvals exceed the maximum of {}", indices.len(), k).into());
+                }
+                for vote_index in indices.iter() {
+                    if *vote_index >= num_of_candidates {
+                        return Err(format!("vote_index {} is out of range for {} candidates", vote_index, num_of_candidates).into());
+                    }
+                    if !vote_m[*vote_index as usize].is_zero() {
+                        return Err(format!("candidate {} is approved twice", vote_index).into());
+                    }
+                    vote_m[*vote_index as usize] = F::one();
+                }
+            }
+            (Ballot::Points(points), BallotMode::Cumulative { bits } | BallotMode::Quadratic { bits } | BallotMode::Weighted { bits }) => {
+                if points.len() as u64 > num_of_candidates {
+                    return Err(format!("{} points are given for {} candidates", points.len(), num_of_candidates).into());
+                }
+                if points.iter().any(|p| *p >> bits != 0) {
+                    return Err(format!("points must be less than 2^{}", bits).into());
+                }
+                if let BallotMode::Cumulative { .. } | BallotMode::Weighted { .. } = ballot_mode {
+                    let budget = ballot_bound.unwrap_or_default();
+                    if points.iter().try_fold(0u64, |sum, p| sum.checked_add(*p)) != Some(budget) {
+                        return Err(format!("points do not add up to the budget of {}", budget).into());
+                    }
+                } else {
+                    let credits = ballot_bound.unwrap_or_default() as u128;
+                    if points.iter().map(|p| (*p as u128) * (*p as u128)).sum::<u128>() > credits {
+                        return Err(format!("quadratic votes cost more than {} credits", credits).into());
+                    }
+                }
+                for (vote_m_i, p) in vote_m.iter_mut().zip(points.iter()) {
+                    *vote_m_i = F::from(*p);
+                }
+            }
+            (Ballot::Ranking(ranking), BallotMode::Ranked) => {
+                if ranking.len() as u64 > num_of_candidates {
+                    return Err(format!("{} candidates are ranked out of {}", ranking.len(), num_of_candidates).into());
+                }
+                for (rank, vote_index) in ranking.iter().enumerate() {
+                    if *vote_index >= num_of_candidates {
+                        return Err(format!("vote_index {} is out of range for {} candidates", vote_index, num_of_candidates).into());
+                    }
+                    if !vote_m[*vote_index as usize].is_zero() {
+                        return Err(format!("candidate {} is ranked twice", vote_index).into());
+                    }
+                    vote_m[*vote_index as usize] = F::from(num_of_candidates - rank as u64);
+                }
+            }
+            (ballot, ballot_mode) => {
+                return Err(format!("{:?} cannot be cast in {:?} mode", ballot, ballot_mode).into());
+            }
+        }
+        Ok(vote_m)
+    }
+
+    /// The `choice` witness of an indexed ballot.
+    pub fn to_choice<F: PrimeField>(&self, ballot_mode: BallotMode, num_of_candidates: u64) -> Result<F, Error> {
+        match (self, ballot_mode) {
+            (Ballot::Single(vote_index), BallotMode::Indexed) => {
+                if *vote_index >= num_of_candidates {
+                    return Err(format!("vote_index {} is out of range for {} candidates", vote_index, num_of_candidates).into());
+                }
+                Ok(F::from(*vote_index))
+            }
+            (ballot, ballot_mode) => Err(format!("{:?} has no choice index in {:?} mode", ballot, ballot_mode).into()),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
+use ark_relations::r1cs::SynthesisError;
+
+/// Enforces `0 <= v < 2^num_bits` by decomposing `v` into `num_bits` witness bits.
+pub fn enforce_in_range<F: PrimeField>(v: &FpVar<F>, num_bits: usize) -> Result<(), SynthesisError> {
+    to_bits_le_in_range(v, num_bits).map(|_| ())
+}
+
+/// The `num_bits` little-endian bits of `v`, which enforces `0 <= v < 2^num_bits`.
+pub fn to_bits_le_in_range<F: PrimeField>(v: &FpVar<F>, num_bits: usize) -> Result<Vec<Boolean<F>>, SynthesisError> {
+    let cs = v.cs();
+    let mut bits = vec![];
+    for i in 0..num_bits {
+        let bit = Boolean::new_witness(cs.clone(), || v.value().map(|v| v.into_bigint().get_bit(i)))?;
+        bits.push(bit);
+    }
+    Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(v)?;
+    Ok(bits)
+}
+
+/// Enforces `a <= b` for `a, b < 2^num_bits`, by range checking `b - a`.
+pub fn enforce_less_or_equal<F: PrimeField>(a: &FpVar<F>, b: &FpVar<F>, num_bits: usize) -> Result<(), SynthesisError> {
+    enforce_in_range(&(b - a), num_bits)
+}
+
+/// Whether `a < b` for any two field elements, by comparing their canonical bits.
+pub fn is_less_than<F: PrimeField>(a: &FpVar<F>, b: &FpVar<F>) -> Result<Boolean<F>, SynthesisError> {
+    // from the least significant bit up, the highest differing bit decides
+    let mut is_less = Boolean::Constant(false);
+    for (a_i, b_i) in a.to_bits_le()?.iter().zip(b.to_bits_le()?.iter()) {
+        is_less = a_i.xor(b_i)?.select(b_i, &is_less)?;
+    }
+    Ok(is_less)
+}
@@ -1,13 +1,17 @@
+use std::collections::{HashMap, VecDeque};
 use std::marker::PhantomData;
 use ark_ff::PrimeField;
-use ark_r1cs_std::fields::fp::FpVar;
-use ark_crypto_primitives::sponge::Absorb;
+use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
+use ark_relations::r1cs::SynthesisError;
+use ark_crypto_primitives::sponge::{poseidon::PoseidonConfig, Absorb};
 
 use ark_crypto_primitives::{
-    crh::poseidon::{self, constraints::{CRHGadget, TwoToOneCRHGadget}},
-    merkle_tree::{constraints::ConfigGadget, Config, IdentityDigestConverter},
+    crh::{poseidon::{self, constraints::{CRHGadget, CRHParametersVar, TwoToOneCRHGadget}}, CRHScheme, CRHSchemeGadget, TwoToOneCRHScheme, TwoToOneCRHSchemeGadget},
+    merkle_tree::{constraints::ConfigGadget, Config, IdentityDigestConverter, Path},
 };
 
+use crate::Error;
+
 pub struct MerkleTreeParams<F: PrimeField> {
     _field: PhantomData<F>,
 }
@@ -35,4 +39,283 @@ where
     type InnerDigest = FpVar<F>;
     type LeafHash = CRHGadget<F>;
     type TwoToOneHash = TwoToOneCRHGadget<F>;
-}
\ No newline at end of file
+}
+
+
+/// Roots of free subtrees of height `0..=tree_height`, whose leaves are all `empty_leaf`.
+fn zero_hashes<F: PrimeField + Absorb>(hash_params: &PoseidonConfig<F>, tree_height: usize, empty_leaf: &[F]) -> Result<Vec<F>, Error> {
+    let mut zeros = vec![poseidon::CRH::<F>::evaluate(hash_params, empty_leaf)?];
+    for i in 0..tree_height {
+        zeros.push(poseidon::TwoToOneCRH::<F>::evaluate(hash_params, zeros[i], zeros[i])?);
+    }
+    Ok(zeros)
+}
+
+/// A Merkle tree of `2^tree_height` leaves that grows by appending, with the same root as a
+/// `MerkleTree<MerkleTreeParams<F>>` whose free slots hold `empty_leaf`.
+///
+/// Only the appended part of the tree is stored. The free subtrees are read from precomputed
+/// hashes, so appending and proving cost `tree_height` hashes each, and the last
+/// `root_history_size` roots stay known for proofs made before the latest appends.
+#[derive(Clone, Debug)]
+pub struct IncrementalMerkleTree<F: PrimeField + Absorb> {
+    hash_params: PoseidonConfig<F>,
+    tree_height: usize,
+    empty_leaf: Vec<F>,
+    zeros: Vec<F>,  // zeros[i] is the root of a free subtree of height i
+    nodes: Vec<Vec<F>>,  // nodes[i] are the appended nodes at height i, leaf digests first
+    root_history: VecDeque<F>,
+    root_history_size: usize,
+}
+
+impl<F: PrimeField + Absorb> IncrementalMerkleTree<F> {
+    pub fn new(
+        hash_params: PoseidonConfig<F>,
+        tree_height: usize,
+        empty_leaf: Vec<F>,
+        root_history_size: usize,
+    ) -> Result<Self, Error> {
+        if tree_height == 0 || tree_height >= usize::BITS as usize {
+            return Err(format!("tree_height {} is not supported", tree_height).into());
+        }
+        if root_history_size == 0 {
+            return Err("root_history_size must be at least 1".into());
+        }
+
+        let zeros = zero_hashes(&hash_params, tree_height, &empty_leaf)?;
+
+        let mut tree = Self {
+            hash_params,
+            tree_height,
+            empty_leaf,
+            zeros,
+            nodes: vec![vec![]; tree_height + 1],
+            root_history: VecDeque::new(),
+            root_history_size,
+        };
+        tree.push_root();
+        Ok(tree)
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        self.nodes[0].len()
+    }
+
+    pub fn root(&self) -> F {
+        self.nodes[self.tree_height].first().copied().unwrap_or(self.zeros[self.tree_height])
+    }
+
+    /// Whether `root` is one of the last `root_history_size` roots.
+    pub fn is_known_root(&self, root: &F) -> bool {
+        self.root_history.contains(root)
+    }
+
+    /// Appends a leaf and returns its index.
+    pub fn append(&mut self, leaf: &[F]) -> Result<usize, Error> {
+        let index = self.num_leaves();
+        if index >> self.tree_height != 0 {
+            return Err("the tree is full".into());
+        }
+        if leaf.len() != self.empty_leaf.len() {
+            return Err(format!("leaves have {} elements, not {}", self.empty_leaf.len(), leaf.len()).into());
+        }
+
+        self.nodes[0].push(poseidon::CRH::<F>::evaluate(&self.hash_params, leaf)?);
+        let mut node_index = index;
+        for height in 0..self.tree_height {
+            let left = self.node(height, node_index & !1);
+            let right = self.node(height, node_index | 1);
+            let parent = poseidon::TwoToOneCRH::<F>::evaluate(&self.hash_params, left, right)?;
+
+            node_index >>= 1;
+            if node_index < self.nodes[height + 1].len() {
+                self.nodes[height + 1][node_index] = parent;
+            } else {
+                self.nodes[height + 1].push(parent);
+            }
+        }
+        self.push_root();
+        Ok(index)
+    }
+
+    /// The membership proof of the leaf at `index` against the current root.
+    pub fn generate_proof(&self, index: usize) -> Result<Path<MerkleTreeParams<F>>, Error> {
+        if index >= self.num_leaves() {
+            return Err(format!("leaf {} is not in the tree", index).into());
+        }
+
+        // auth_path goes from the root down, and does not hold the leaf sibling
+        let leaf_sibling_hash = self.node(0, index ^ 1);
+        let mut auth_path = vec![];
+        for height in 1..self.tree_height {
+            auth_path.push(self.node(height, (index >> height) ^ 1));
+        }
+        auth_path.reverse();
+
+        Ok(Path {
+            leaf_sibling_hash,
+            auth_path,
+            leaf_index: index,
+        })
+    }
+
+    fn node(&self, height: usize, index: usize) -> F {
+        self.nodes[height].get(index).copied().unwrap_or(self.zeros[height])
+    }
+
+    fn push_root(&mut self) {
+        if self.root_history.len() == self.root_history_size {
+            self.root_history.pop_front();
+        }
+        self.root_history.push_back(self.root());
+    }
+}
+
+
+/// The root of an `IncrementalMerkleTree` of height `tree_height` after appending `leaves`,
+/// computed in the circuit. The free subtrees hash constants, which costs no constraints.
+pub fn incremental_root_gadget<F: PrimeField + Absorb>(
+    hash_params: &CRHParametersVar<F>,
+    tree_height: usize,
+    empty_leaf: &[F],
+    leaves: &[Vec<FpVar<F>>],
+) -> Result<FpVar<F>, SynthesisError> {
+    if tree_height >= usize::BITS as usize || leaves.len() >> tree_height != 0 {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    let empty_leaf = empty_leaf.iter().map(|x| FpVar::constant(*x)).collect::<Vec<_>>();
+    let mut zero = CRHGadget::<F>::evaluate(hash_params, &empty_leaf)?;
+    let mut nodes = leaves.iter().map(|leaf| CRHGadget::<F>::evaluate(hash_params, leaf)).collect::<Result<Vec<_>, _>>()?;
+    for _ in 0..tree_height {
+        if nodes.len() % 2 == 1 {
+            nodes.push(zero.clone());
+        }
+        nodes = nodes.chunks(2).map(|pair| TwoToOneCRHGadget::<F>::evaluate(hash_params, &pair[0], &pair[1])).collect::<Result<Vec<_>, _>>()?;
+        zero = TwoToOneCRHGadget::<F>::evaluate(hash_params, &zero, &zero)?;
+    }
+    Ok(nodes.pop().unwrap_or(zero))
+}
+
+/// Largest height of a `SparseMerkleTree`, as `leaf_pos` is a `UInt32` in the circuit.
+pub const SPARSE_TREE_MAX_HEIGHT: usize = 32;
+
+/// A Merkle tree of `2^tree_height` leaves, most of them `empty_leaf`, with the same root and
+/// proofs as a `MerkleTree<MerkleTreeParams<F>>` over all of them.
+///
+/// Only the nodes above set leaves are stored, so memory grows with the number of registered
+/// voters times `tree_height` rather than with `2^tree_height`.
+#[derive(Clone, Debug)]
+pub struct SparseMerkleTree<F: PrimeField + Absorb> {
+    hash_params: PoseidonConfig<F>,
+    tree_height: usize,
+    empty_leaf: Vec<F>,
+    zeros: Vec<F>,
+    nodes: HashMap<(usize, u64), F>,  // (height, index) -> node, leaf digests at height 0
+    num_leaves: usize,
+}
+
+impl<F: PrimeField + Absorb> SparseMerkleTree<F> {
+    pub fn new(hash_params: PoseidonConfig<F>, tree_height: usize, empty_leaf: Vec<F>) -> Result<Self, Error> {
+        if tree_height == 0 || tree_height > SPARSE_TREE_MAX_HEIGHT {
+            return Err(format!("tree_height {} is not supported", tree_height).into());
+        }
+        let zeros = zero_hashes(&hash_params, tree_height, &empty_leaf)?;
+
+        Ok(Self {
+            hash_params,
+            tree_height,
+            empty_leaf,
+            zeros,
+            nodes: HashMap::new(),
+            num_leaves: 0,
+        })
+    }
+
+    pub fn root(&self) -> F {
+        self.node(self.tree_height, 0)
+    }
+
+    /// Number of leaves that are set.
+    pub fn num_leaves(&self) -> usize {
+        self.num_leaves
+    }
+
+    /// Sets the leaf at `index`, replacing the previous one.
+    pub fn insert(&mut self, index: u64, leaf: &[F]) -> Result<(), Error> {
+        if leaf.len() != self.empty_leaf.len() {
+            return Err(format!("leaves have {} elements, not {}", self.empty_leaf.len(), leaf.len()).into());
+        }
+        let digest = poseidon::CRH::<F>::evaluate(&self.hash_params, leaf)?;
+        self.update(index, Some(digest))
+    }
+
+    /// Resets the leaf at `index` to `empty_leaf`, and drops the nodes that became empty.
+    pub fn remove(&mut self, index: u64) -> Result<(), Error> {
+        self.update(index, None)
+    }
+
+    /// The membership proof of the leaf at `index` against the current root.
+    pub fn generate_proof(&self, index: u64) -> Result<Path<MerkleTreeParams<F>>, Error> {
+        self.check_index(index)?;
+
+        // auth_path goes from the root down, and does not hold the leaf sibling
+        let leaf_sibling_hash = self.node(0, index ^ 1);
+        let mut auth_path = vec![];
+        for height in 1..self.tree_height {
+            auth_path.push(self.node(height, (index >> height) ^ 1));
+        }
+        auth_path.reverse();
+
+        Ok(Path {
+            leaf_sibling_hash,
+            auth_path,
+            leaf_index: index as usize,
+        })
+    }
+
+    fn check_index(&self, index: u64) -> Result<(), Error> {
+        if index >> self.tree_height != 0 {
+            return Err(format!("leaf {} is out of range for a tree of height {}", index, self.tree_height).into());
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, index: u64, digest: Option<F>) -> Result<(), Error> {
+        self.check_index(index)?;
+
+        self.set_node(0, index, digest);
+        let mut node_index = index;
+        for height in 0..self.tree_height {
+            let left = self.node(height, node_index & !1);
+            let right = self.node(height, node_index | 1);
+            node_index >>= 1;
+
+            let parent = if left == self.zeros[height] && right == self.zeros[height] {
+                None
+            } else {
+                Some(poseidon::TwoToOneCRH::<F>::evaluate(&self.hash_params, left, right)?)
+            };
+            self.set_node(height + 1, node_index, parent);
+        }
+        Ok(())
+    }
+
+    fn node(&self, height: usize, index: u64) -> F {
+        self.nodes.get(&(height, index)).copied().unwrap_or(self.zeros[height])
+    }
+
+    fn set_node(&mut self, height: usize, index: u64, node: Option<F>) {
+        let was_set = match node {
+            Some(node) => self.nodes.insert((height, index), node),
+            None => self.nodes.remove(&(height, index)),
+        }.is_some();
+        if height == 0 {
+            match (was_set, node.is_some()) {
+                (false, true) => self.num_leaves += 1,
+                (true, false) => self.num_leaves -= 1,
+                _ => {}
+            }
+        }
+    }
+}
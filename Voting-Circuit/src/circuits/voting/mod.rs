@@ -1,19 +1,58 @@
 pub mod voting_circuit;
+pub mod ballot;
+pub mod gadgets;
 pub mod merkle_tree;
+pub mod quad_merkle_tree;
+pub mod registry;
+pub mod revocation;
+pub mod semaphore;
 pub mod test;
 pub mod poseidon_params;
 pub mod parser;
 pub mod prover;
 pub mod setup;
-pub mod key_utils;
 
 use ark_crypto_primitives::sponge::Absorb;
 use ark_ec::CurveGroup;
 use ark_ff::PrimeField;
 use ark_r1cs_std::groups::{CurveVar, GroupOpsBounds};
 
+use crate::circuits::voting::ballot::{Ballot, Contest};
 use crate::Error;
 
+/// A mock election for `MockingCircuit::generate_circuit`. The default is a plain election of
+/// two mock voters in round 1, voted in by voter 1, over a voter tree of height 10.
+#[derive(Clone, Debug)]
+pub struct ElectionConfig<F: PrimeField> {
+    pub tree_height: u64,
+    pub contract_addr: F,  // address of the PseVoting deployment, see parser::from_address_to_base_field
+    pub voting_round: u64,
+    pub num_of_voters: u64,
+    pub voter_pos: u64,  // index of the voter
+    pub version: Option<u64>,  // number of earlier ballots of the voter, None when re-voting is off
+    pub revoked_voters: Option<Vec<u64>>,  // indices of the revoked voters, None without a revocation list
+    pub relayer: Option<(F, F)>,  // submitter address and fee, see parser::from_address_to_base_field
+    pub semaphore: bool,  // voters hold Semaphore identities and the voter tree is their group
+    pub quad_tree: bool,  // the voter tree has arity 4, which needs an even tree_height
+}
+
+impl<F: PrimeField> Default for ElectionConfig<F> {
+    fn default() -> Self {
+        Self {
+            tree_height: 10,
+            contract_addr: F::zero(),
+            voting_round: 1,
+            num_of_voters: 2,
+            voter_pos: 1,
+            version: None,
+            revoked_voters: None,
+            relayer: None,
+            semaphore: false,
+            quad_tree: false,
+        }
+    }
+}
+
 pub trait MockingCircuit<C, GG>
 where
     C: CurveGroup,
@@ -28,15 +67,11 @@ where
 
     fn generate_circuit(
         g: C::Affine,
-        ck: Vec::<C::Affine>,
+        h: C::Affine,
         sk: C::BaseField,
         pk: C::Affine,
-        tree_height: u64,
-        voting_round: u64,
-        num_of_candidates: u64,
-        num_of_voters: u64,
-        vote_index: u64,
-        voter_pos: u64,
-        candidate_limit: u64,
+        contests: Vec<Contest>,
+        ballots: Vec<Ballot>,
+        config: ElectionConfig<C::BaseField>,
     ) -> Result<Self::Output, Error>;
 }
@@ -1,7 +1,8 @@
 use serde::{Serialize, Deserialize};
 use std::fs;
 use serde_json::Result;
-use ark_ec::CurveGroup;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::PrimeField;
 use std::str::FromStr;
 
 type C = ark_ed_on_bn254::EdwardsProjective;
@@ -12,6 +13,7 @@ pub struct User {
     pub sk: String,
     pub pk: Vec<String>,
     pub addr: String,
+    pub weight: String,
 }
 
 #[allow(non_snake_case)]
@@ -28,6 +30,7 @@ pub struct ParsedUser {
     pub sk: <C as CurveGroup>::BaseField,
     pub pk: <C as CurveGroup>::Affine,
     pub addr: <C as CurveGroup>::BaseField,
+    pub weight: u64,
 }
 
 fn read_mock_data() -> Result<MockData> {
@@ -46,6 +49,25 @@ pub fn from_str_to_base_field(x: &str) -> <C as CurveGroup>::BaseField {
     <<C as CurveGroup>::BaseField>::from_str(x).unwrap()
 }
 
+/// Reads a `0x`-prefixed Ethereum address as a field element, as `uint(uint160(address))` in Solidity.
+pub fn from_address_to_base_field(addr: &str) -> std::result::Result<<C as CurveGroup>::BaseField, crate::Error> {
+    let bytes = hex::decode(addr.trim_start_matches("0x"))?;
+    if bytes.len() != 20 {
+        return Err(format!("{} is not a 20-byte address", addr).into());
+    }
+    Ok(<<C as CurveGroup>::BaseField>::from_be_bytes_mod_order(&bytes))
+}
+
+/// Reads a decimal `uint` (e.g. a fee in wei) as a field element. Fails if it does not fit in the field.
+pub fn from_uint_to_base_field(value: &str) -> std::result::Result<<C as CurveGroup>::BaseField, crate::Error> {
+    let value = num_bigint::BigUint::from_str(value)?;
+    let modulus: num_bigint::BigUint = <<C as CurveGroup>::BaseField>::MODULUS.into();
+    if value >= modulus {
+        return Err("value does not fit in the field".into());
+    }
+    Ok(<<C as CurveGroup>::BaseField>::from_le_bytes_mod_order(&value.to_bytes_le()))
+}
+
 pub fn get_users() -> Result<Vec<User>> {
     let mock_data = read_mock_data()?;
     Ok(mock_data.Users)
@@ -82,17 +104,24 @@ pub fn get_x() -> Result<<C as CurveGroup>::BaseField> {
     Ok(x)
 }
 
-pub fn parse_user(user: &User) -> Result<ParsedUser> {
+pub fn get_h() -> Result<<C as CurveGroup>::Affine> {
+    let g = get_g()?;
+    let x = get_x()?;
+    Ok(g.mul_bigint(x.into_bigint()).into_affine())
+}
+
+pub fn parse_user(user: &User) -> std::result::Result<ParsedUser, crate::Error> {
     let sk = <<C as CurveGroup>::BaseField>::from_str(&user.sk).unwrap();
     let addr = <<C as CurveGroup>::BaseField>::from_str(&user.addr).unwrap();
     let pk_x = &user.pk[0];
     let pk_y = &user.pk[1];
     let pk = from_base_field_to_affine(pk_x, pk_y)?;
+    let weight = u64::from_str(&user.weight).map_err(|e| format!("weight {} is not a u64: {}", user.weight, e))?;
 
-    Ok(ParsedUser { sk, pk, addr })
+    Ok(ParsedUser { sk, pk, addr, weight })
 }
 
-pub fn parse_all_users() -> Result<Vec<ParsedUser>> {
+pub fn parse_all_users() -> std::result::Result<Vec<ParsedUser>, crate::Error> {
     let users = get_users()?;
-    users.iter().map(|user| parse_user(user)).collect()
+    users.iter().map(parse_user).collect()
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use ark_ff::PrimeField;
-use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_crypto_primitives::sponge::poseidon::{find_poseidon_ark_and_mds, PoseidonConfig};
 
 pub fn get_poseidon_params<F: PrimeField>() -> PoseidonConfig<F> {
     let full_rounds = 8;
@@ -202,3 +202,34 @@ pub fn get_poseidon_params<F: PrimeField>() -> PoseidonConfig<F> {
 
     PoseidonConfig::new(full_rounds, partial_rounds, alpha, mds, ark, 2, 1)
 }
+
+
+/// Width-5 (rate 4) parameters for the nodes of the arity-4 voter tree: the rate-4 entry of
+/// the arkworks defaults optimized for constraints (`PoseidonDefaultConfigEntry::new(4, 5, 8, 56, 0)`),
+/// alpha 5 with 8 full and 56 partial rounds, and the round constants and MDS matrix of the
+/// Grain LFSR.
+pub fn get_quad_poseidon_params<F: PrimeField>() -> PoseidonConfig<F> {
+    let full_rounds = 8;
+    let partial_rounds = 56;
+    let alpha = 5;
+
+    let (ark, mds) = find_poseidon_ark_and_mds::<F>(F::MODULUS_BIT_SIZE as u64, 4, full_rounds as u64, partial_rounds as u64, 0);
+
+    PoseidonConfig::new(full_rounds, partial_rounds, alpha, mds, ark, 4, 1)
+}
+
+
+/// Partial rounds of circomlib's Poseidon for 1 to 8 inputs (state width 2 to 9).
+const CIRCOM_PARTIAL_ROUNDS: [usize; 8] = [56, 57, 56, 60, 60, 63, 64, 63];
+
+/// Parameters of circomlib's Poseidon over BN254 for `num_of_inputs` inputs: alpha 5, 8 full
+/// rounds, and the round constants and MDS matrix of the reference Grain LFSR.
+pub fn get_circom_poseidon_params<F: PrimeField>(num_of_inputs: usize) -> PoseidonConfig<F> {
+    let full_rounds = 8;
+    let partial_rounds = CIRCOM_PARTIAL_ROUNDS[num_of_inputs - 1];
+    let alpha = 5;
+
+    let (ark, mds) = find_poseidon_ark_and_mds::<F>(F::MODULUS_BIT_SIZE as u64, num_of_inputs, full_rounds as u64, partial_rounds as u64, 0);
+
+    PoseidonConfig::new(full_rounds, partial_rounds, alpha, mds, ark, num_of_inputs, 1)
+}
@@ -11,7 +11,5 @@ type GG = ark_ed_on_bn254::constraints::EdwardsVar;
 pub fn voting_prove(pk: ProvingKey<Bn254>, circuit: VotingCircuit<C, GG>) -> Proof<Bn254> {
     let rng = &mut thread_rng();
 
-    let proof = Groth16::<Bn254>::prove(&pk, circuit, rng).unwrap();
-
-    proof
+    Groth16::<Bn254>::prove(&pk, circuit, rng).unwrap()
 }
\ No newline at end of file
@@ -0,0 +1,143 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
+use ark_relations::r1cs::{Namespace, SynthesisError};
+use ark_crypto_primitives::{
+    crh::{poseidon::{constraints::{CRHGadget, CRHParametersVar}, CRH}, CRHScheme, CRHSchemeGadget},
+    sponge::{poseidon::PoseidonConfig, Absorb},
+};
+use std::borrow::Borrow;
+
+use crate::Error;
+
+/// Membership proof in a `QuadMerkleTree`: the three siblings of the node on every level,
+/// from the leaf up, in the order they appear among the four children.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuadPath<F: PrimeField> {
+    pub leaf_index: usize,
+    pub siblings: Vec<[F; 3]>,
+}
+
+/// A Merkle tree where every node is `CRH(c0 || c1 || c2 || c3)` under width-5 Poseidon
+/// parameters, so that a tree of `4^depth` leaves is half as deep as the binary one.
+/// `test_voting_quad_tree` prints the constraints of a voting proof over both trees.
+#[derive(Clone, Debug)]
+pub struct QuadMerkleTree<F: PrimeField> {
+    levels: Vec<Vec<F>>,  // levels[0] are the leaf digests, the last level is the root
+}
+
+/// The four children of a node, with `node` at `position` among its `siblings`.
+fn children<T: Clone>(node: &T, siblings: &[T; 3], position: usize) -> Vec<T> {
+    let mut children = siblings.to_vec();
+    children.insert(position, node.clone());
+    children
+}
+
+impl<F: PrimeField + Absorb> QuadMerkleTree<F> {
+    pub fn new(hash_params: &PoseidonConfig<F>, leaves: &[Vec<F>]) -> Result<Self, Error> {
+        if leaves.len() < 4 || !leaves.len().is_power_of_two() || !leaves.len().trailing_zeros().is_multiple_of(2) {
+            return Err(format!("{} leaves do not make a full arity-4 tree", leaves.len()).into());
+        }
+
+        let mut level = vec![];
+        for leaf in leaves.iter() {
+            level.push(CRH::<F>::evaluate(hash_params, leaf.as_slice())?);
+        }
+        let mut levels = vec![level];
+        while levels[levels.len() - 1].len() > 1 {
+            let mut level = vec![];
+            for quad in levels[levels.len() - 1].chunks(4) {
+                level.push(CRH::<F>::evaluate(hash_params, quad)?);
+            }
+            levels.push(level);
+        }
+        Ok(Self { levels })
+    }
+
+    pub fn root(&self) -> F {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    pub fn generate_proof(&self, index: usize) -> Result<QuadPath<F>, Error> {
+        if index >= self.levels[0].len() {
+            return Err(format!("leaf {} is not in the tree", index).into());
+        }
+
+        let mut siblings = vec![];
+        for (depth, level) in self.levels[..self.levels.len() - 1].iter().enumerate() {
+            let node_index = index >> (2 * depth);
+            let first = node_index & !3;
+            let mut quad = level[first..first + 4].to_vec();
+            quad.remove(node_index & 3);
+            siblings.push([quad[0], quad[1], quad[2]]);
+        }
+        Ok(QuadPath { leaf_index: index, siblings })
+    }
+}
+
+impl<F: PrimeField + Absorb> QuadPath<F> {
+    pub fn verify(&self, hash_params: &PoseidonConfig<F>, root: &F, leaf: &[F]) -> Result<bool, Error> {
+        let mut node = CRH::<F>::evaluate(hash_params, leaf)?;
+        for (depth, siblings) in self.siblings.iter().enumerate() {
+            let position = (self.leaf_index >> (2 * depth)) & 3;
+            node = CRH::<F>::evaluate(hash_params, children(&node, siblings, position))?;
+        }
+        Ok(node == *root)
+    }
+}
+
+
+#[derive(Clone, Debug)]
+pub struct QuadPathVar<F: PrimeField> {
+    pub siblings: Vec<[FpVar<F>; 3]>,
+}
+
+impl<F: PrimeField> AllocVar<QuadPath<F>, F> for QuadPathVar<F> {
+    fn new_variable<T: Borrow<QuadPath<F>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let path = f()?;
+
+        let mut siblings = vec![];
+        for quad in path.borrow().siblings.iter() {
+            let quad = Vec::<FpVar<F>>::new_variable(cs.clone(), || Ok(quad.to_vec()), mode)?;
+            siblings.push([quad[0].clone(), quad[1].clone(), quad[2].clone()]);
+        }
+        Ok(Self { siblings })
+    }
+}
+
+impl<F: PrimeField + Absorb> QuadPathVar<F> {
+    /// Whether `leaf` is at the position given by `leaf_pos` (little-endian bits, two per level)
+    /// under `root`.
+    pub fn verify_membership(
+        &self,
+        hash_params: &CRHParametersVar<F>,
+        root: &FpVar<F>,
+        leaf: &[FpVar<F>],
+        leaf_pos: &[Boolean<F>],
+    ) -> Result<Boolean<F>, SynthesisError> {
+        if leaf_pos.len() < 2 * self.siblings.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut node = CRHGadget::<F>::evaluate(hash_params, leaf)?;
+        for (siblings, bits) in self.siblings.iter().zip(leaf_pos.chunks(2)) {
+            // position = b0 + 2 * b1 selects where node goes among [s0, s1, s2]
+            let [s0, s1, s2] = siblings;
+            let (b0, b1) = (&bits[0], &bits[1]);
+            let is_first = b0.or(b1)?.not();
+            let is_last = b0.and(b1)?;
+
+            let c0 = is_first.select(&node, s0)?;
+            let c1 = b1.select(s1, &b0.select(&node, s0)?)?;
+            let c2 = b1.select(&b0.select(s2, &node)?, s1)?;
+            let c3 = is_last.select(&node, s2)?;
+            node = CRHGadget::<F>::evaluate(hash_params, &[c0, c1, c2, c3])?;
+        }
+        node.is_eq(root)
+    }
+}
@@ -0,0 +1,153 @@
+use ark_ec::CurveGroup;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
+use ark_relations::r1cs::SynthesisError;
+use ark_crypto_primitives::{
+    crh::{poseidon::{self, constraints::{CRHGadget, CRHParametersVar}}, CRHScheme, CRHSchemeGadget, TwoToOneCRHScheme},
+    merkle_tree::Path,
+    sponge::{poseidon::PoseidonConfig, Absorb},
+};
+use rayon::prelude::*;
+
+use crate::circuits::voting::merkle_tree::MerkleTreeParams;
+use crate::Error;
+
+/// The commitment `CRH(pk || salt)` a voter registers for one election. A fresh salt per
+/// election keeps registrations unlinkable, unlike `addr = CRH(pk)`.
+pub fn identity_commitment<F: PrimeField + Absorb>(hash_params: &PoseidonConfig<F>, pk_x: F, pk_y: F, salt: F) -> Result<F, Error> {
+    poseidon::CRH::<F>::evaluate(hash_params, vec![pk_x, pk_y, salt])
+}
+
+/// The coordinates `[pk_x, pk_y]` of a key with its commitment `cm`.
+pub type KeyCommitmentVar<F> = (Vec<FpVar<F>>, FpVar<F>);
+
+/// Enforces `pk = g^sk` and returns `([pk_x, pk_y], cm)` with `cm = CRH(pk || salt)`, the
+/// in-circuit `identity_commitment` of the key owner.
+pub fn key_commitment_gadget<C, GG>(
+    g: &GG,
+    hash_params: &CRHParametersVar<C::BaseField>,
+    sk: &FpVar<C::BaseField>,
+    pk: &GG,
+    salt: &FpVar<C::BaseField>,
+) -> Result<KeyCommitmentVar<C::BaseField>, SynthesisError>
+where
+    C: CurveGroup,
+    GG: CurveVar<C, C::BaseField>,
+    <C as CurveGroup>::BaseField: PrimeField + Absorb,
+{
+    let sk_bits = sk.to_bits_le()?;
+    let pk_computed = g.scalar_mul_le(sk_bits.iter())?;
+    pk_computed.enforce_equal(pk)?;
+
+    let pk_bits = pk.to_bits_le()?;
+    let pk_x = Boolean::le_bits_to_fp_var(&pk_bits[..pk_bits.len() / 2])?;
+    let pk_y = Boolean::le_bits_to_fp_var(&pk_bits[pk_bits.len() / 2..])?;
+
+    let cm = CRHGadget::<C::BaseField>::evaluate(hash_params, &[pk_x.clone(), pk_y.clone(), salt.clone()])?;
+    Ok((vec![pk_x, pk_y], cm))
+}
+
+/// The leaf of an empty slot in the voter tree, `[0]` or `[0, 0]` when weighted.
+pub fn empty_voter_leaf<F: PrimeField>(weighted: bool) -> Vec<F> {
+    vec![F::zero(); 1 + weighted as usize]
+}
+
+/// Builds the leaves of the registered voters: `[cm]`, or `[cm, weight]` when `weights` are given.
+pub fn registered_voter_leaves<F: PrimeField>(commitments: &[F], weights: Option<&[u64]>) -> Result<Vec<Vec<F>>, Error> {
+    match weights {
+        Some(weights) => {
+            if weights.len() != commitments.len() {
+                return Err("every voter needs exactly one weight".into());
+            }
+            Ok(commitments.iter().zip(weights.iter()).map(|(cm, weight)| vec![*cm, F::from(*weight)]).collect())
+        }
+        None => Ok(commitments.iter().map(|cm| vec![*cm]).collect()),
+    }
+}
+
+/// Builds all `2^tree_height` leaves of the voter tree, the registered ones padded with
+/// `empty_voter_leaf`.
+pub fn voter_leaves<F: PrimeField>(
+    commitments: &[F],
+    weights: Option<&[u64]>,
+    tree_height: u64,
+) -> Result<Vec<Vec<F>>, Error> {
+    let num_leaves = 2_usize.pow(tree_height as u32);
+    if commitments.len() > num_leaves {
+        return Err(format!("{} voters do not fit in a tree of height {}", commitments.len(), tree_height).into());
+    }
+
+    let mut leaves = registered_voter_leaves(commitments, weights)?;
+    leaves.resize(num_leaves, empty_voter_leaf(weights.is_some()));
+    Ok(leaves)
+}
+
+
+/// The voter tree held by the registrar, with every level kept so that the paths of all
+/// voters can be handed out at once. It has the same root and paths as `MerkleTree::new`
+/// over the same leaves.
+#[derive(Clone, Debug)]
+pub struct VoterTree<F: PrimeField> {
+    levels: Vec<Vec<F>>,  // levels[0] are the leaf digests, the last level is the root
+}
+
+impl<F: PrimeField + Absorb> VoterTree<F> {
+    /// Hashes the leaves, then every level of the tree, in parallel.
+    pub fn build(hash_params: &PoseidonConfig<F>, leaves: &[Vec<F>]) -> Result<Self, Error> {
+        if leaves.len() < 2 || !leaves.len().is_power_of_two() {
+            return Err(format!("{} leaves do not make a full binary tree", leaves.len()).into());
+        }
+
+        let leaf_digests = leaves.par_iter()
+            .map(|leaf| poseidon::CRH::<F>::evaluate(hash_params, leaf.as_slice()).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<F>, String>>()?;
+
+        let mut levels = vec![leaf_digests];
+        while levels[levels.len() - 1].len() > 1 {
+            let level = levels[levels.len() - 1].par_chunks(2)
+                .map(|pair| poseidon::TwoToOneCRH::<F>::evaluate(hash_params, pair[0], pair[1]).map_err(|e| e.to_string()))
+                .collect::<Result<Vec<F>, String>>()?;
+            levels.push(level);
+        }
+        Ok(Self { levels })
+    }
+
+    pub fn root(&self) -> F {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn generate_proof(&self, index: usize) -> Result<Path<MerkleTreeParams<F>>, Error> {
+        if index >= self.num_leaves() {
+            return Err(format!("leaf {} is not in the tree", index).into());
+        }
+
+        // auth_path goes from the root down, and does not hold the leaf sibling
+        let leaf_sibling_hash = self.levels[0][index ^ 1];
+        let mut auth_path = vec![];
+        for height in 1..self.levels.len() - 1 {
+            auth_path.push(self.levels[height][(index >> height) ^ 1]);
+        }
+        auth_path.reverse();
+
+        Ok(Path {
+            leaf_sibling_hash,
+            auth_path,
+            leaf_index: index,
+        })
+    }
+
+    /// The paths of the first `num_of_voters` leaves, generated in parallel.
+    pub fn generate_proofs(&self, num_of_voters: usize) -> Result<Vec<Path<MerkleTreeParams<F>>>, Error> {
+        if num_of_voters > self.num_leaves() {
+            return Err(format!("{} voters do not fit in {} leaves", num_of_voters, self.num_leaves()).into());
+        }
+        let proofs = (0..num_of_voters).into_par_iter()
+            .map(|index| self.generate_proof(index).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(proofs)
+    }
+}
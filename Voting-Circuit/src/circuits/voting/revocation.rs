@@ -0,0 +1,90 @@
+use ark_ff::PrimeField;
+use ark_crypto_primitives::{merkle_tree::Path, sponge::{poseidon::PoseidonConfig, Absorb}};
+
+use crate::circuits::voting::merkle_tree::{MerkleTreeParams, SparseMerkleTree};
+use crate::Error;
+
+/// A leaf `[value, next_index, next_value]` of the revocation tree. The leaves form a linked
+/// list sorted by `value`, where `next_value` is zero for the largest revoked value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexedLeaf<F: PrimeField> {
+    pub value: F,
+    pub next_index: u64,
+    pub next_value: F,
+}
+
+impl<F: PrimeField> IndexedLeaf<F> {
+    pub fn to_vec(&self) -> Vec<F> {
+        vec![self.value, F::from(self.next_index), self.next_value]
+    }
+
+    /// Whether `value` falls strictly between this leaf and the next one.
+    pub fn is_low_leaf_of(&self, value: &F) -> bool {
+        self.value < *value && (self.next_value.is_zero() || *value < self.next_value)
+    }
+}
+
+/// The low leaf of a value that is not revoked, with its membership proof.
+#[derive(Clone, Debug)]
+pub struct NonMembershipProof<F: PrimeField + Absorb> {
+    pub low_leaf: IndexedLeaf<F>,
+    pub low_leaf_pos: u64,
+    pub path: Path<MerkleTreeParams<F>>,
+}
+
+/// An indexed Merkle tree of revoked `addr`s. The low leaf of an `addr` that is not revoked
+/// proves its non-membership.
+#[derive(Clone, Debug)]
+pub struct IndexedMerkleTree<F: PrimeField + Absorb> {
+    tree: SparseMerkleTree<F>,
+    leaves: Vec<IndexedLeaf<F>>,
+}
+
+impl<F: PrimeField + Absorb> IndexedMerkleTree<F> {
+    /// An empty list, which holds the zero leaf that every value is above.
+    pub fn new(hash_params: PoseidonConfig<F>, tree_height: usize) -> Result<Self, Error> {
+        let zero_leaf = IndexedLeaf { value: F::zero(), next_index: 0, next_value: F::zero() };
+        let mut tree = SparseMerkleTree::new(hash_params, tree_height, vec![F::zero(); 3])?;
+        tree.insert(0, &zero_leaf.to_vec())?;
+
+        Ok(Self { tree, leaves: vec![zero_leaf] })
+    }
+
+    pub fn root(&self) -> F {
+        self.tree.root()
+    }
+
+    /// The index of the leaf right below `value`. Fails if `value` is revoked.
+    pub fn low_leaf(&self, value: &F) -> Result<usize, Error> {
+        if value.is_zero() {
+            return Err("zero cannot be revoked".into());
+        }
+        self.leaves.iter().position(|leaf| leaf.is_low_leaf_of(value)).ok_or_else(|| "the value is revoked".into())
+    }
+
+    /// Revokes `value` and returns the index of its leaf.
+    pub fn insert(&mut self, value: F) -> Result<usize, Error> {
+        let low_index = self.low_leaf(&value)?;
+        let index = self.leaves.len();
+        let low_leaf = self.leaves[low_index];
+        let leaf = IndexedLeaf { value, next_index: low_leaf.next_index, next_value: low_leaf.next_value };
+        let low_leaf = IndexedLeaf { next_index: index as u64, next_value: value, ..low_leaf };
+
+        self.tree.insert(index as u64, &leaf.to_vec())?;
+        self.tree.insert(low_index as u64, &low_leaf.to_vec())?;
+        self.leaves.push(leaf);
+        self.leaves[low_index] = low_leaf;
+        Ok(index)
+    }
+
+    /// The membership proof of the leaf at `index`, which is `[0, 0, 0]` for an empty slot.
+    pub fn generate_proof(&self, index: u64) -> Result<Path<MerkleTreeParams<F>>, Error> {
+        self.tree.generate_proof(index)
+    }
+
+    pub fn non_membership_proof(&self, value: &F) -> Result<NonMembershipProof<F>, Error> {
+        let low_index = self.low_leaf(value)?;
+        let path = self.tree.generate_proof(low_index as u64)?;
+        Ok(NonMembershipProof { low_leaf: self.leaves[low_index], low_leaf_pos: low_index as u64, path })
+    }
+}
@@ -0,0 +1,218 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSystemRef, Namespace, SynthesisError};
+use ark_crypto_primitives::sponge::{
+    constraints::CryptographicSpongeVar,
+    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig, PoseidonSponge},
+    Absorb, CryptographicSponge,
+};
+use std::borrow::Borrow;
+
+use crate::circuits::voting::poseidon_params::get_circom_poseidon_params;
+use crate::Error;
+
+/// circomlib's `Poseidon(n)` for one and two inputs, the hash of Semaphore identities and groups.
+///
+/// It is the arkworks permutation with a zero capacity element in front of the inputs,
+/// except that the output is that first element of the state rather than the rate part.
+#[derive(Clone, Debug)]
+pub struct CircomPoseidon<F: PrimeField> {
+    params: [PoseidonConfig<F>; 2],  // params[n - 1] hash n inputs
+}
+
+impl<F: PrimeField + Absorb> Default for CircomPoseidon<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField + Absorb> CircomPoseidon<F> {
+    pub fn new() -> Self {
+        Self { params: [get_circom_poseidon_params(1), get_circom_poseidon_params(2)] }
+    }
+
+    fn params(&self, num_of_inputs: usize) -> Option<&PoseidonConfig<F>> {
+        num_of_inputs.checked_sub(1).and_then(|i| self.params.get(i))
+    }
+
+    pub fn hash(&self, inputs: &[F]) -> Result<F, Error> {
+        let params = self.params(inputs.len()).ok_or(format!("no Poseidon parameters for {} inputs", inputs.len()))?;
+        let mut sponge = PoseidonSponge::new(params);
+        sponge.absorb(&inputs.to_vec());
+        let _: Vec<F> = sponge.squeeze_field_elements(1);
+        Ok(sponge.state[0])
+    }
+
+    pub fn hash_gadget(&self, cs: ConstraintSystemRef<F>, inputs: &[FpVar<F>]) -> Result<FpVar<F>, SynthesisError> {
+        let params = self.params(inputs.len()).ok_or(SynthesisError::Unsatisfiable)?;
+        let mut sponge = PoseidonSpongeVar::new(cs, params);
+        sponge.absorb(&inputs.to_vec())?;
+        sponge.squeeze_field_elements(1)?;
+        Ok(sponge.state[0].clone())
+    }
+}
+
+
+/// A Semaphore identity. Its commitment `Poseidon(Poseidon(nullifier, trapdoor))` is the
+/// member of a Semaphore group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SemaphoreIdentity<F: PrimeField> {
+    pub trapdoor: F,
+    pub nullifier: F,
+}
+
+impl<F: PrimeField + Absorb> SemaphoreIdentity<F> {
+    pub fn secret(&self, poseidon: &CircomPoseidon<F>) -> Result<F, Error> {
+        poseidon.hash(&[self.nullifier, self.trapdoor])
+    }
+
+    pub fn commitment(&self, poseidon: &CircomPoseidon<F>) -> Result<F, Error> {
+        poseidon.hash(&[self.secret(poseidon)?])
+    }
+}
+
+pub fn identity_commitment_gadget<F: PrimeField + Absorb>(
+    cs: ConstraintSystemRef<F>,
+    poseidon: &CircomPoseidon<F>,
+    trapdoor: &FpVar<F>,
+    nullifier: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    let secret = poseidon.hash_gadget(cs.clone(), &[nullifier.clone(), trapdoor.clone()])?;
+    poseidon.hash_gadget(cs, &[secret])
+}
+
+
+/// Membership proof in a `SemaphoreGroup`: the sibling on every level, from the leaf up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SemaphorePath<F: PrimeField> {
+    pub leaf_index: usize,
+    pub siblings: Vec<F>,
+}
+
+/// A Semaphore group: a binary tree of fixed depth over identity commitments, where a node
+/// is `Poseidon(left, right)` and empty leaves hold `zero_value`. Leaves are not hashed.
+#[derive(Clone, Debug)]
+pub struct SemaphoreGroup<F: PrimeField> {
+    poseidon: CircomPoseidon<F>,
+    depth: usize,
+    zeros: Vec<F>,  // zeros[i] is the root of an empty subtree of height i
+    members: Vec<F>,
+}
+
+impl<F: PrimeField + Absorb> SemaphoreGroup<F> {
+    pub fn new(depth: usize, zero_value: F) -> Result<Self, Error> {
+        if depth == 0 || depth > 32 {
+            return Err(format!("a group cannot have depth {}", depth).into());
+        }
+        let poseidon = CircomPoseidon::new();
+        let mut zeros = vec![zero_value];
+        for i in 0..depth {
+            zeros.push(poseidon.hash(&[zeros[i], zeros[i]])?);
+        }
+        Ok(Self { poseidon, depth, zeros, members: vec![] })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn members(&self) -> &[F] {
+        &self.members
+    }
+
+    /// Adds an identity commitment and returns its index.
+    pub fn add_member(&mut self, commitment: F) -> Result<usize, Error> {
+        if self.members.len() >= 1 << self.depth {
+            return Err("the group is full".into());
+        }
+        self.members.push(commitment);
+        Ok(self.members.len() - 1)
+    }
+
+    /// Every level of the non-empty part of the tree, from the members up to the root.
+    fn levels(&self) -> Result<Vec<Vec<F>>, Error> {
+        let mut levels = vec![self.members.clone()];
+        for height in 0..self.depth {
+            let level = &levels[height];
+            let mut parents = vec![];
+            for pair in level.chunks(2) {
+                let right = pair.get(1).copied().unwrap_or(self.zeros[height]);
+                parents.push(self.poseidon.hash(&[pair[0], right])?);
+            }
+            levels.push(parents);
+        }
+        Ok(levels)
+    }
+
+    pub fn root(&self) -> Result<F, Error> {
+        Ok(self.levels()?[self.depth].first().copied().unwrap_or(self.zeros[self.depth]))
+    }
+
+    pub fn generate_proof(&self, index: usize) -> Result<SemaphorePath<F>, Error> {
+        if index >= self.members.len() {
+            return Err(format!("member {} is not in the group", index).into());
+        }
+        let levels = self.levels()?;
+        let siblings = (0..self.depth)
+            .map(|height| levels[height].get((index >> height) ^ 1).copied().unwrap_or(self.zeros[height]))
+            .collect();
+        Ok(SemaphorePath { leaf_index: index, siblings })
+    }
+}
+
+impl<F: PrimeField + Absorb> SemaphorePath<F> {
+    pub fn root(&self, poseidon: &CircomPoseidon<F>, leaf: F) -> Result<F, Error> {
+        let mut node = leaf;
+        for (height, sibling) in self.siblings.iter().enumerate() {
+            node = match (self.leaf_index >> height) & 1 {
+                0 => poseidon.hash(&[node, *sibling])?,
+                _ => poseidon.hash(&[*sibling, node])?,
+            };
+        }
+        Ok(node)
+    }
+}
+
+
+#[derive(Clone, Debug)]
+pub struct SemaphorePathVar<F: PrimeField> {
+    pub siblings: Vec<FpVar<F>>,
+}
+
+impl<F: PrimeField> AllocVar<SemaphorePath<F>, F> for SemaphorePathVar<F> {
+    fn new_variable<T: Borrow<SemaphorePath<F>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let path = f()?;
+
+        let siblings = Vec::<FpVar<F>>::new_variable(cs, || Ok(path.borrow().siblings.clone()), mode)?;
+        Ok(Self { siblings })
+    }
+}
+
+impl<F: PrimeField + Absorb> SemaphorePathVar<F> {
+    /// The root of the group holding `leaf` at the position given by `leaf_pos` (little-endian bits).
+    pub fn root(
+        &self,
+        cs: ConstraintSystemRef<F>,
+        poseidon: &CircomPoseidon<F>,
+        leaf: &FpVar<F>,
+        leaf_pos: &[Boolean<F>],
+    ) -> Result<FpVar<F>, SynthesisError> {
+        if leaf_pos.len() < self.siblings.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut node = leaf.clone();
+        for (sibling, is_right) in self.siblings.iter().zip(leaf_pos.iter()) {
+            let left = is_right.select(sibling, &node)?;
+            let right = is_right.select(&node, sibling)?;
+            node = poseidon.hash_gadget(cs.clone(), &[left, right])?;
+        }
+        Ok(node)
+    }
+}
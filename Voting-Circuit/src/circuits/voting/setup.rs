@@ -2,14 +2,14 @@ use ark_bn254::Bn254;
 use ark_groth16::{Groth16, PreparedVerifyingKey, ProvingKey, VerifyingKey};
 use ark_std::rand::thread_rng;
 use ark_crypto_primitives::snark::SNARK;
-use crate::circuits::voting::key_utils::*;
+use crate::circuits::key_utils::KeyFiles;
 
 use super::voting_circuit::VotingCircuit;
 
 type C = ark_ed_on_bn254::EdwardsProjective;
 type GG = ark_ed_on_bn254::constraints::EdwardsVar;
 
-
+pub const KEY_FILES: KeyFiles = KeyFiles::new("./src/keys/voting/", "voting");
 
 pub fn voting_setup(circuit: VotingCircuit<C, GG>) -> (ProvingKey<Bn254>, VerifyingKey<Bn254>, PreparedVerifyingKey<Bn254>) {
     let rng = &mut thread_rng();
@@ -18,7 +18,7 @@ pub fn voting_setup(circuit: VotingCircuit<C, GG>) -> (ProvingKey<Bn254>, Verify
 
     let pvk = Groth16::<Bn254>::process_vk(&vk).unwrap();
 
-    store_pk_vk(pk.clone(), vk.clone());
+    KEY_FILES.store_pk_vk(pk.clone(), vk.clone());
 
     (pk, vk, pvk)
 }
\ No newline at end of file
@@ -1,13 +1,13 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod test {
     use ark_bn254::Bn254;
-    use ark_ec::AffineRepr;
-    use ark_ff::Field;
-    use crate::circuits::voting::{voting_circuit::VotingCircuit, MockingCircuit};
+    use crate::circuits::voting::{voting_circuit::VotingCircuit, ElectionConfig, MockingCircuit};
+    use crate::circuits::voting::ballot::{Ballot, BallotMode, Contest};
     use ark_relations::r1cs::ConstraintSystem;
     use ark_groth16::Groth16;
     use ark_crypto_primitives::snark::SNARK;
-    use crate::circuits::voting::parser::{get_ck, get_g, get_user, parse_user};
+    use crate::circuits::voting::parser::{from_address_to_base_field, get_g, get_h, get_user, parse_user};
     use crate::circuits::voting::{
         setup::voting_setup,
         prover::voting_prove
@@ -18,29 +18,36 @@ mod test {
 
     type F = ark_bn254::Fr;
 
+    const CONTRACT_ADDR: &str = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
+
+
+    /// A plain election at CONTRACT_ADDR. Tests change what they need with `..election()`.
+    fn election() -> ElectionConfig<F> {
+        ElectionConfig {
+            contract_addr: from_address_to_base_field(CONTRACT_ADDR).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn contest(ballot_mode: BallotMode, ballot_bound: Option<u64>, num_of_candidates: u64, candidate_limit: u64) -> Contest {
+        Contest { ballot_mode, ballot_bound, num_of_candidates, candidate_limit }
+    }
 
     fn make_mocking_circuit() -> VotingCircuit<C, GG> {
-        let tree_height = 10;       // constant
-        let voting_round = 1;       // mutable
         let num_of_candidates = 2; // mutable
-        let num_of_voters = 2;     // mutable
-        let vote_index = 1;         // mutable  (num_of_candidates보다 작아야함)
-        let voter_pos = 1;          // mutable (Mock data에 addr이 0 ~ 9)까지 준비되어있음. num_of_voters보다 작아야함
+        let ballot = Ballot::Single(1); // mutable  (num_of_candidates보다 작아야함)
         let candidate_limit = 2;   // constant
-        
-        
-        let g = get_g().unwrap();
-        let ck = get_ck().unwrap();
-        let user = get_user(voter_pos.clone() as usize).unwrap();
-        let parsed_user = parse_user(&user).unwrap();
-        let sk = parsed_user.sk;
-        let pk = parsed_user.pk;
 
-        let test_circuit = <VotingCircuit<C, GG> as MockingCircuit<C, GG>>::generate_circuit(
-            g, ck, sk, pk, tree_height, voting_round, num_of_candidates, num_of_voters, vote_index, voter_pos, candidate_limit
-        ).unwrap();
+        make_mocking_circuit_with(vec![contest(BallotMode::Plurality, None, num_of_candidates, candidate_limit)], vec![ballot], election()).unwrap()
+    }
+
+    /// The circuit of the voter at `config.voter_pos` (Mock data에 addr이 0 ~ 9까지 준비되어있음).
+    fn make_mocking_circuit_with(contests: Vec<Contest>, ballots: Vec<Ballot>, config: ElectionConfig<F>) -> Result<VotingCircuit<C, GG>, crate::Error> {
+        let g = get_g()?;
+        let h = get_h()?;
+        let user = parse_user(&get_user(config.voter_pos as usize)?)?;
 
-        test_circuit
+        <VotingCircuit<C, GG> as MockingCircuit<C, GG>>::generate_circuit(g, h, user.sk, user.pk, contests, ballots, config)
     }
 
     #[test]
@@ -54,6 +61,1087 @@ mod test {
         assert!(cs.is_satisfied().unwrap());
     }
 
+    #[test]
+    fn test_voting_fewer_candidates() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let test_circuit = make_mocking_circuit_with(vec![contest(BallotMode::Plurality, None, 2, 4)], vec![Ballot::Single(1)], election()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_unused_slot() {
+        use ark_ec::{AffineRepr, CurveGroup};
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let mut test_circuit = make_mocking_circuit_with(vec![contest(BallotMode::Plurality, None, 2, 4)], vec![Ballot::Single(1)], election()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        // move the vote from slot 1 to slot 3, which has no candidate (vote_r[3] = 0)
+        let g = test_circuit.g;
+        let mut vote_m = test_circuit.witness.contests[0].vote_m.clone().unwrap();
+        let mut vote_cm = test_circuit.instance.contests[0].vote_cm.clone().unwrap();
+        vote_m.swap(1, 3);
+        vote_cm[1] = (vote_cm[1].into_group() - g).into_affine();
+        vote_cm[3] = (vote_cm[3].into_group() + g).into_affine();
+        test_circuit.witness.contests[0].vote_m = Some(vote_m);
+        test_circuit.instance.contests[0].vote_cm = Some(vote_cm);
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_indexed() {
+        use ark_ec::{AffineRepr, CurveGroup};
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let test_circuit = make_mocking_circuit_with(vec![contest(BallotMode::Indexed, None, 2, 4)], vec![Ballot::Single(1)], election()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        assert_eq!(test_circuit.witness.contests[0].choice, Some(F::from(1u64)));
+        assert!(test_circuit.witness.contests[0].vote_m.is_none());
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // a boolean slot takes one constraint where plurality mode takes two
+        for candidate_limit in [2, 8, 16] {
+            let indexed_cs = ConstraintSystem::<F>::new_ref();
+            make_mocking_circuit_with(vec![contest(BallotMode::Indexed, None, 2, candidate_limit)], vec![Ballot::Single(1)], election()).unwrap().generate_constraints(indexed_cs.clone()).unwrap();
+            let plurality_cs = ConstraintSystem::<F>::new_ref();
+            make_mocking_circuit_with(vec![contest(BallotMode::Plurality, None, 2, candidate_limit)], vec![Ballot::Single(1)], election()).unwrap().generate_constraints(plurality_cs.clone()).unwrap();
+            println!("candidate_limit {}: indexed {} vs plurality {} constraints", candidate_limit, indexed_cs.num_constraints(), plurality_cs.num_constraints());
+            assert_eq!(indexed_cs.num_constraints() + candidate_limit as usize - 1, plurality_cs.num_constraints());
+        }
+
+        // a choice past the last slot has no one-hot vote_m
+        let mut wrong_circuit = test_circuit.clone();
+        wrong_circuit.witness.contests[0].choice = Some(F::from(4u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // choose slot 3, which has no candidate
+        let mut wrong_circuit = test_circuit;
+        let g = wrong_circuit.g;
+        let mut vote_cm = wrong_circuit.instance.contests[0].vote_cm.clone().unwrap();
+        vote_cm[1] = (vote_cm[1].into_group() - g).into_affine();
+        vote_cm[3] = (vote_cm[3].into_group() + g).into_affine();
+        wrong_circuit.instance.contests[0].vote_cm = Some(vote_cm);
+        wrong_circuit.witness.contests[0].choice = Some(F::from(3u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_abstain() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let test_circuit = make_mocking_circuit_with(vec![contest(BallotMode::Abstainable, None, 2, 2)], vec![Ballot::Abstain], election()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // a blank ballot is rejected by a plurality circuit
+        let mut test_circuit = test_circuit;
+        test_circuit.ballot_modes[0] = BallotMode::Plurality;
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_approval() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let test_circuit = make_mocking_circuit_with(vec![contest(BallotMode::Approval, Some(2), 3, 4)], vec![Ballot::Approval(vec![0, 2])], election()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // two approvals exceed k = 1
+        let mut test_circuit = test_circuit;
+        test_circuit.instance.contests[0].ballot_bound = Some(F::from(1u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_cumulative() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let ballot_mode = BallotMode::Cumulative { bits: 3 };
+        let test_circuit = make_mocking_circuit_with(vec![contest(ballot_mode, Some(5), 3, 4)], vec![Ballot::Points(vec![3, 0, 2])], election()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // the points do not add up to a budget of 6
+        let mut test_circuit = test_circuit;
+        test_circuit.instance.contests[0].ballot_bound = Some(F::from(6u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_quadratic() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let ballot_mode = BallotMode::Quadratic { bits: 4 };
+        let test_circuit = make_mocking_circuit_with(vec![contest(ballot_mode, Some(30), 3, 4)], vec![Ballot::Points(vec![5, 0, 2])], election()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // 5^2 + 2^2 = 29 credits exceed 28
+        let mut test_circuit = test_circuit;
+        test_circuit.instance.contests[0].ballot_bound = Some(F::from(28u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // points wide enough for the sum of squares to wrap around the field are rejected
+        let mut wide_circuit = make_mocking_circuit_with(vec![contest(ballot_mode, Some(30), 3, 4)], vec![Ballot::Points(vec![5, 0, 2])], election()).unwrap();
+        wide_circuit.ballot_modes[0] = BallotMode::Quadratic { bits: 200 };
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        assert!(wide_circuit.generate_constraints(cs.clone()).is_err());
+    }
+
+    #[test]
+    fn test_voting_weighted() {
+        use ark_ec::{AffineRepr, CurveGroup};
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        // voter 1 has a weight of 3 in Mock.json
+        let ballot_mode = BallotMode::Weighted { bits: 2 };
+        let test_circuit = make_mocking_circuit_with(vec![contest(ballot_mode, None, 2, 2)], vec![Ballot::Points(vec![2, 1])], election()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // a weight that is not in the voter tree, with points {3, 1} that spend all of it
+        let mut test_circuit = test_circuit;
+        let g = test_circuit.g;
+        let mut vote_m = test_circuit.witness.contests[0].vote_m.clone().unwrap();
+        let mut vote_cm = test_circuit.instance.contests[0].vote_cm.clone().unwrap();
+        vote_m[0] = F::from(3u64);
+        vote_cm[0] = (vote_cm[0].into_group() + g).into_affine();
+        test_circuit.witness.contests[0].vote_m = Some(vote_m);
+        test_circuit.instance.contests[0].vote_cm = Some(vote_cm);
+        test_circuit.witness.weight = Some(F::from(4u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // a weight that is not a number
+        let mut user = get_user(1).unwrap();
+        user.weight = "three".to_string();
+        assert!(parse_user(&user).is_err());
+
+        // a voter outside the registered ones has no weight
+        let contests = vec![contest(ballot_mode, None, 2, 2)];
+        assert!(make_mocking_circuit_with(contests, vec![Ballot::Points(vec![2, 1])], ElectionConfig { num_of_voters: 1, ..election() }).is_err());
+    }
+
+    #[test]
+    fn test_voting_ranked() {
+        use ark_ec::{AffineRepr, CurveGroup};
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        // candidate 2 first, then candidate 0; candidate 1 is unranked
+        let test_circuit = make_mocking_circuit_with(vec![contest(BallotMode::Ranked, None, 3, 4)], vec![Ballot::Ranking(vec![2, 0])], election()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        assert_eq!(test_circuit.witness.contests[0].vote_m.clone().unwrap(), vec![F::from(2u64), F::from(0u64), F::from(3u64), F::from(0u64)]);
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // give candidate 1 the same score as candidate 0
+        let mut test_circuit = test_circuit;
+        let g = test_circuit.g;
+        let mut vote_m = test_circuit.witness.contests[0].vote_m.clone().unwrap();
+        let mut vote_cm = test_circuit.instance.contests[0].vote_cm.clone().unwrap();
+        vote_m[1] = F::from(2u64);
+        vote_cm[1] = (vote_cm[1].into_group() + g + g).into_affine();
+        let mut wrong_circuit = test_circuit.clone();
+        wrong_circuit.witness.contests[0].vote_m = Some(vote_m);
+        wrong_circuit.instance.contests[0].vote_cm = Some(vote_cm);
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // leave a gap below candidate 2 with {3, 1} instead of {3, 2}, which no ranking scores
+        let mut vote_m = test_circuit.witness.contests[0].vote_m.clone().unwrap();
+        let mut vote_cm = test_circuit.instance.contests[0].vote_cm.clone().unwrap();
+        vote_m[0] = F::from(1u64);
+        vote_cm[0] = (vote_cm[0].into_group() - g).into_affine();
+        test_circuit.witness.contests[0].vote_m = Some(vote_m);
+        test_circuit.instance.contests[0].vote_cm = Some(vote_cm);
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_multi_contest() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let contests = vec![
+            contest(BallotMode::Plurality, None, 2, 2),
+            contest(BallotMode::Approval, Some(2), 3, 4),
+        ];
+        let ballots = vec![Ballot::Single(0), Ballot::Approval(vec![1, 2])];
+        let test_circuit = make_mocking_circuit_with(contests.clone(), ballots, election()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        assert_eq!(test_circuit.instance.contests.len(), 2);
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // the approval contest only allows one approval
+        let mut test_circuit = test_circuit;
+        test_circuit.instance.contests[1].ballot_bound = Some(F::from(1u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // every contest needs a ballot
+        assert!(make_mocking_circuit_with(contests, vec![Ballot::Single(0)], election()).is_err());
+    }
+
+    #[test]
+    fn test_voting_revote() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let contests = vec![contest(BallotMode::Plurality, None, 2, 2)];
+
+        // the first ballot has no previous one
+        let first = make_mocking_circuit_with(contests.clone(), vec![Ballot::Single(0)], ElectionConfig { version: Some(0), ..election() }).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        assert_eq!(first.instance.prev_sn, Some(F::from(0u64)));
+        first.clone().generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // the re-vote links to the serial number of the first ballot
+        let second = make_mocking_circuit_with(contests.clone(), vec![Ballot::Single(1)], ElectionConfig { version: Some(1), ..election() }).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        assert_eq!(second.instance.prev_sn, first.instance.sn);
+        assert_ne!(second.instance.sn, first.instance.sn);
+        second.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // claiming to be a first ballot again
+        let mut wrong_circuit = second;
+        wrong_circuit.instance.prev_sn = Some(F::from(0u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_revocation() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        use crate::circuits::voting::poseidon_params::get_poseidon_params;
+        use crate::circuits::voting::revocation::IndexedMerkleTree;
+        let contests = vec![contest(BallotMode::Plurality, None, 2, 2)];
+
+        // voter 0 is revoked, voter 1 votes
+        let test_circuit = make_mocking_circuit_with(contests.clone(), vec![Ballot::Single(0)], ElectionConfig { revoked_voters: Some(vec![0]), ..election() }).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // a revoked voter cannot build the proof
+        assert!(make_mocking_circuit_with(contests, vec![Ballot::Single(0)], ElectionConfig { revoked_voters: Some(vec![0, 1]), ..election() }).is_err());
+
+        // nor prove against a revocation root that lists them
+        let mut wrong_circuit = test_circuit;
+        let mut revocation_tree = IndexedMerkleTree::new(get_poseidon_params::<F>(), 10).unwrap();
+        revocation_tree.insert(wrong_circuit.witness.addr.unwrap()).unwrap();
+        wrong_circuit.instance.revocation_root = Some(revocation_tree.root());
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_revocation_empty_slot() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        use crate::circuits::voting::poseidon_params::get_poseidon_params;
+        use crate::circuits::voting::parser::from_uint_to_base_field;
+        use crate::circuits::voting::revocation::IndexedMerkleTree;
+        let contests = vec![contest(BallotMode::Plurality, None, 2, 2)];
+
+        // voters 0 and 1 are revoked, but voter 1 passes an empty slot off as the zero low leaf
+        let mut wrong_circuit = make_mocking_circuit_with(contests, vec![Ballot::Single(0)], ElectionConfig { revoked_voters: Some(vec![0]), ..election() }).unwrap();
+        let mut revocation_tree = IndexedMerkleTree::new(get_poseidon_params::<F>(), 10).unwrap();
+        revocation_tree.insert(from_uint_to_base_field(&get_user(0).unwrap().addr).unwrap()).unwrap();
+        revocation_tree.insert(wrong_circuit.witness.addr.unwrap()).unwrap();
+        wrong_circuit.instance.revocation_root = Some(revocation_tree.root());
+        wrong_circuit.witness.low_leaf = Some(vec![F::from(0u64); 3]);
+        wrong_circuit.witness.low_leaf_pos = Some(5);
+        wrong_circuit.witness.revocation_proof = Some(revocation_tree.generate_proof(5).unwrap());
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_indexed_merkle_tree() {
+        use crate::circuits::voting::poseidon_params::get_poseidon_params;
+        use crate::circuits::voting::revocation::IndexedMerkleTree;
+        let hash_params = get_poseidon_params::<F>();
+        let mut tree = IndexedMerkleTree::new(hash_params.clone(), 4).unwrap();
+
+        for value in [30u64, 10, 20] {
+            tree.insert(F::from(value)).unwrap();
+        }
+        assert!(tree.insert(F::from(20u64)).is_err());
+        assert!(tree.non_membership_proof(&F::from(10u64)).is_err());
+        assert!(tree.non_membership_proof(&F::from(0u64)).is_err());
+
+        // values between and above the revoked ones, up to the largest field element
+        for (value, low_value, next_value) in [(15u64, 10u64, 20u64), (25, 20, 30), (5, 0, 10)] {
+            let proof = tree.non_membership_proof(&F::from(value)).unwrap();
+            assert_eq!((proof.low_leaf.value, proof.low_leaf.next_value), (F::from(low_value), F::from(next_value)));
+            assert!(proof.path.verify(&hash_params, &hash_params, &tree.root(), proof.low_leaf.to_vec().as_slice()).unwrap());
+        }
+        let proof = tree.non_membership_proof(&-F::from(1u64)).unwrap();
+        assert_eq!((proof.low_leaf.value, proof.low_leaf.next_value), (F::from(30u64), F::from(0u64)));
+    }
+
+    #[test]
+    fn test_is_less_than() {
+        use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+        use crate::circuits::voting::gadgets::is_less_than;
+        let cs = ConstraintSystem::<F>::new_ref();
+        let values = [F::from(0u64), F::from(1u64), F::from(1u64 << 40), -F::from(2u64), -F::from(1u64)];
+
+        for a in values.iter() {
+            for b in values.iter() {
+                let a_var = FpVar::new_witness(cs.clone(), || Ok(*a)).unwrap();
+                let b_var = FpVar::new_witness(cs.clone(), || Ok(*b)).unwrap();
+                assert_eq!(is_less_than(&a_var, &b_var).unwrap().value().unwrap(), a < b);
+            }
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voter_leaves() {
+        use crate::circuits::voting::registry::{empty_voter_leaf, registered_voter_leaves, voter_leaves};
+        let addrs = vec![F::from(7u64), F::from(8u64)];
+
+        let leaves = voter_leaves(&addrs, None, 2).unwrap();
+        assert_eq!(leaves, vec![vec![F::from(7u64)], vec![F::from(8u64)], vec![F::from(0u64)], vec![F::from(0u64)]]);
+
+        let leaves = voter_leaves(&addrs, Some(&[2, 5]), 1).unwrap();
+        assert_eq!(leaves, vec![vec![F::from(7u64), F::from(2u64)], vec![F::from(8u64), F::from(5u64)]]);
+
+        assert!(voter_leaves(&addrs, Some(&[2]), 1).is_err());
+        assert!(voter_leaves(&addrs, None, 0).is_err());
+
+        // without padding, for the trees that precompute their free slots
+        let leaves = registered_voter_leaves(&addrs, Some(&[2, 5])).unwrap();
+        assert_eq!(leaves, vec![vec![F::from(7u64), F::from(2u64)], vec![F::from(8u64), F::from(5u64)]]);
+        assert_eq!(empty_voter_leaf::<F>(true), vec![F::from(0u64); 2]);
+    }
+
+    #[test]
+    fn test_voting_tall_tree() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let contests = vec![contest(BallotMode::Weighted { bits: 2 }, None, 2, 2)];
+        let ballots = vec![Ballot::Points(vec![1, 2])];
+
+        // 2^20 slots, of which only the registered voters are hashed
+        let test_circuit = make_mocking_circuit_with(contests, ballots, ElectionConfig { tree_height: 20, ..election() }).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        assert_eq!(test_circuit.witness.tree_proof.as_ref().unwrap().auth_path.len(), 19);
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    fn mock_voter_tree_leaves(num_of_voters: u64, tree_height: u64) -> Vec<Vec<F>> {
+        use crate::circuits::voting::registry::voter_leaves;
+        let addrs = (0..num_of_voters).map(|i| F::from(1000 + i)).collect::<Vec<_>>();
+        voter_leaves(&addrs, None, tree_height).unwrap()
+    }
+
+    #[test]
+    fn test_voter_tree() {
+        use ark_crypto_primitives::merkle_tree::MerkleTree;
+        use crate::circuits::voting::merkle_tree::MerkleTreeParams;
+        use crate::circuits::voting::poseidon_params::get_poseidon_params;
+        use crate::circuits::voting::registry::VoterTree;
+        let hash_params = get_poseidon_params::<F>();
+        let leaves = mock_voter_tree_leaves(6, 3);
+
+        let tree = VoterTree::build(&hash_params, &leaves).unwrap();
+        let full_tree = MerkleTree::<MerkleTreeParams<F>>::new(&hash_params, &hash_params, leaves.clone()).unwrap();
+        assert_eq!(tree.root(), full_tree.root());
+
+        let proofs = tree.generate_proofs(6).unwrap();
+        for (i, proof) in proofs.iter().enumerate() {
+            assert_eq!(proof.auth_path, full_tree.generate_proof(i).unwrap().auth_path);
+            assert!(proof.verify(&hash_params, &hash_params, &tree.root(), leaves[i].as_slice()).unwrap());
+        }
+        assert!(tree.generate_proofs(9).is_err());
+        assert!(VoterTree::build(&hash_params, &leaves[..3]).is_err());
+    }
+
+    /// `cargo test --release bench_voter_tree -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_voter_tree() {
+        use std::time::Instant;
+        use ark_crypto_primitives::merkle_tree::MerkleTree;
+        use crate::circuits::voting::merkle_tree::MerkleTreeParams;
+        use crate::circuits::voting::poseidon_params::get_poseidon_params;
+        use crate::circuits::voting::registry::VoterTree;
+        let hash_params = get_poseidon_params::<F>();
+        let num_of_voters = 10000;
+        let leaves = mock_voter_tree_leaves(num_of_voters, 14);
+
+        let start = Instant::now();
+        let full_tree = MerkleTree::<MerkleTreeParams<F>>::new(&hash_params, &hash_params, leaves.clone()).unwrap();
+        let proofs = (0..num_of_voters as usize).map(|i| full_tree.generate_proof(i).unwrap()).collect::<Vec<_>>();
+        println!("sequential MerkleTree::new + {} paths: {:?}", proofs.len(), start.elapsed());
+
+        let start = Instant::now();
+        let tree = VoterTree::build(&hash_params, &leaves).unwrap();
+        let proofs = tree.generate_proofs(num_of_voters as usize).unwrap();
+        println!("parallel VoterTree::build + {} paths: {:?} on {} threads", proofs.len(), start.elapsed(), rayon::current_num_threads());
+
+        assert_eq!(tree.root(), full_tree.root());
+    }
+
+    #[test]
+    fn test_incremental_merkle_tree() {
+        use ark_crypto_primitives::merkle_tree::MerkleTree;
+        use crate::circuits::voting::merkle_tree::{IncrementalMerkleTree, MerkleTreeParams};
+        use crate::circuits::voting::poseidon_params::get_poseidon_params;
+        use crate::circuits::voting::registry::voter_leaves;
+        let hash_params = get_poseidon_params::<F>();
+        let tree_height = 3;
+
+        let mut tree = IncrementalMerkleTree::new(hash_params.clone(), tree_height, vec![F::from(0u64)], 3).unwrap();
+        let mut addrs = vec![];
+        for i in 0..5u64 {
+            addrs.push(F::from(100 + i));
+            assert_eq!(tree.append(&[addrs[i as usize]]).unwrap(), i as usize);
+
+            // same root and proofs as a tree rebuilt from the padded leaves
+            let leaves = voter_leaves(&addrs, None, tree_height as u64).unwrap();
+            let full_tree = MerkleTree::<MerkleTreeParams<F>>::new(&hash_params, &hash_params, leaves.clone()).unwrap();
+            assert_eq!(tree.root(), full_tree.root());
+            for (j, leaf) in leaves.iter().enumerate().take(addrs.len()) {
+                let proof = tree.generate_proof(j).unwrap();
+                assert_eq!(proof.auth_path, full_tree.generate_proof(j).unwrap().auth_path);
+                assert!(proof.verify(&hash_params, &hash_params, &tree.root(), leaf.as_slice()).unwrap());
+            }
+        }
+        assert!(tree.generate_proof(5).is_err());
+        assert!(tree.append(&[F::from(1u64), F::from(2u64)]).is_err());
+
+        // a proof made before two more registrations is still checked against a known root
+        let old_root = tree.root();
+        let old_proof = tree.generate_proof(0).unwrap();
+        tree.append(&[F::from(105u64)]).unwrap();
+        tree.append(&[F::from(106u64)]).unwrap();
+        assert!(tree.is_known_root(&old_root));
+        assert!(old_proof.verify(&hash_params, &hash_params, &old_root, [F::from(100u64)].as_slice()).unwrap());
+
+        tree.append(&[F::from(107u64)]).unwrap();
+        assert!(!tree.is_known_root(&old_root));
+        assert!(tree.append(&[F::from(108u64)]).is_err());
+    }
+
+    #[test]
+    fn test_sparse_merkle_tree() {
+        use ark_crypto_primitives::crh::poseidon::constraints::CRHParametersVar;
+        use ark_crypto_primitives::merkle_tree::constraints::PathVar;
+        use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, eq::EqGadget, fields::fp::FpVar, uint32::UInt32};
+        use crate::circuits::voting::merkle_tree::{IncrementalMerkleTree, MerkleTreeParams, MerkleTreeParamsVar, SparseMerkleTree};
+        use crate::circuits::voting::poseidon_params::get_poseidon_params;
+        let hash_params = get_poseidon_params::<F>();
+
+        // same root as the incremental tree over the same leaves
+        let mut sparse_tree = SparseMerkleTree::new(hash_params.clone(), 4, vec![F::from(0u64)]).unwrap();
+        let mut incremental_tree = IncrementalMerkleTree::new(hash_params.clone(), 4, vec![F::from(0u64)], 1).unwrap();
+        let empty_root = sparse_tree.root();
+        for i in 0..3u64 {
+            sparse_tree.insert(i, &[F::from(100 + i)]).unwrap();
+            incremental_tree.append(&[F::from(100 + i)]).unwrap();
+        }
+        assert_eq!(sparse_tree.root(), incremental_tree.root());
+        assert_eq!(sparse_tree.generate_proof(1).unwrap().auth_path, incremental_tree.generate_proof(1).unwrap().auth_path);
+        sparse_tree.insert(1, &[F::from(101u64)]).unwrap();
+        assert_eq!(sparse_tree.num_leaves(), 3);
+        for i in 0..3u64 {
+            sparse_tree.remove(i).unwrap();
+        }
+        assert_eq!(sparse_tree.root(), empty_root);
+        assert_eq!(sparse_tree.num_leaves(), 0);
+        assert!(sparse_tree.insert(16, &[F::from(1u64)]).is_err());
+
+        // a few voters spread over 2^32 slots
+        let mut tree = SparseMerkleTree::new(hash_params.clone(), 32, vec![F::from(0u64)]).unwrap();
+        let positions = [0u64, 7, 1 << 20, (1 << 32) - 1];
+        for (i, pos) in positions.iter().enumerate() {
+            tree.insert(*pos, &[F::from(200 + i as u64)]).unwrap();
+        }
+        assert_eq!(tree.num_leaves(), positions.len());
+        assert!(SparseMerkleTree::new(hash_params.clone(), 33, vec![F::from(0u64)]).is_err());
+
+        for (i, pos) in positions.iter().enumerate() {
+            let leaf = [F::from(200 + i as u64)];
+            let proof = tree.generate_proof(*pos).unwrap();
+            assert!(proof.verify(&hash_params, &hash_params, &tree.root(), leaf.as_slice()).unwrap());
+
+            // the proof is checked by PathVar as in the voting circuit
+            let cs = ConstraintSystem::<F>::new_ref();
+            let params = CRHParametersVar::<F>::new_constant(cs.clone(), hash_params.clone()).unwrap();
+            let root = FpVar::new_input(cs.clone(), || Ok(tree.root())).unwrap();
+            let leaf_g = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(leaf.to_vec())).unwrap();
+            let leaf_pos = UInt32::new_witness(cs.clone(), || Ok(*pos as u32)).unwrap().to_bits_le();
+            let mut cw = PathVar::<MerkleTreeParams<F>, F, MerkleTreeParamsVar<F>>::new_witness(cs.clone(), || Ok(proof)).unwrap();
+            cw.set_leaf_position(leaf_pos);
+            cw.verify_membership(&params, &params, &root, &leaf_g).unwrap().enforce_equal(&Boolean::Constant(true)).unwrap();
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_voting_sparse_tree() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        use ark_ec::AffineRepr;
+        use crate::circuits::voting::merkle_tree::SparseMerkleTree;
+        use crate::circuits::voting::registry::identity_commitment;
+        let contests = vec![contest(BallotMode::Plurality, None, 2, 2)];
+        let tree_height = 24;
+        let test_circuit = make_mocking_circuit_with(contests, vec![Ballot::Single(1)], ElectionConfig { tree_height, ..election() }).unwrap();
+
+        // the voter's leaf, far from the other registered voter in a sparse tree of the same height
+        let pk = test_circuit.witness.pk.unwrap();
+        let (pk_x, pk_y) = pk.xy().unwrap();
+        let cm = identity_commitment(&test_circuit.hash_params, *pk_x, *pk_y, test_circuit.witness.salt.unwrap()).unwrap();
+        let leaf_pos = (1u64 << 23) + 5;
+        let mut tree = SparseMerkleTree::new(test_circuit.hash_params.clone(), tree_height as usize, vec![F::from(0u64)]).unwrap();
+        tree.insert(0, &[F::from(1000u64)]).unwrap();
+        tree.insert(leaf_pos, &[cm]).unwrap();
+
+        let mut test_circuit = test_circuit;
+        test_circuit.instance.root = Some(tree.root());
+        test_circuit.witness.leaf_pos = Some(leaf_pos as u32);
+        test_circuit.witness.tree_proof = Some(tree.generate_proof(leaf_pos).unwrap());
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // the path does not open any other position
+        test_circuit.witness.leaf_pos = Some(leaf_pos as u32 + 1);
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_quad_merkle_tree() {
+        use ark_crypto_primitives::crh::poseidon::constraints::CRHParametersVar;
+        use ark_crypto_primitives::merkle_tree::{constraints::PathVar, MerkleTree};
+        use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, eq::EqGadget, fields::fp::FpVar, uint32::UInt32};
+        use crate::circuits::voting::merkle_tree::{MerkleTreeParams, MerkleTreeParamsVar};
+        use crate::circuits::voting::poseidon_params::{get_poseidon_params, get_quad_poseidon_params};
+        use crate::circuits::voting::quad_merkle_tree::{QuadMerkleTree, QuadPathVar};
+        let hash_params = get_poseidon_params::<F>();
+        let quad_hash_params = get_quad_poseidon_params::<F>();
+        let tree_height = 10;
+        let voter_pos = 613;
+        let leaves = mock_voter_tree_leaves(700, tree_height);
+
+        let quad_tree = QuadMerkleTree::new(&quad_hash_params, &leaves).unwrap();
+        let quad_proof = quad_tree.generate_proof(voter_pos).unwrap();
+        assert_eq!(quad_proof.siblings.len(), tree_height as usize / 2);
+        assert!(quad_proof.verify(&quad_hash_params, &quad_tree.root(), &leaves[voter_pos]).unwrap());
+        assert!(!quad_proof.verify(&quad_hash_params, &quad_tree.root(), &leaves[voter_pos + 1]).unwrap());
+        assert!(QuadMerkleTree::new(&quad_hash_params, &leaves[..512]).is_err());
+
+        // membership part of VotingCircuit with the binary tree
+        let tree = MerkleTree::<MerkleTreeParams<F>>::new(&hash_params, &hash_params, leaves.clone()).unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+        let params = CRHParametersVar::<F>::new_constant(cs.clone(), hash_params.clone()).unwrap();
+        let root = FpVar::new_input(cs.clone(), || Ok(tree.root())).unwrap();
+        let leaf_g = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(leaves[voter_pos].clone())).unwrap();
+        let leaf_pos = UInt32::new_witness(cs.clone(), || Ok(voter_pos as u32)).unwrap().to_bits_le();
+        let before = cs.num_constraints();
+        let mut cw = PathVar::<MerkleTreeParams<F>, F, MerkleTreeParamsVar<F>>::new_witness(cs.clone(), || Ok(tree.generate_proof(voter_pos).unwrap())).unwrap();
+        cw.set_leaf_position(leaf_pos);
+        cw.verify_membership(&params, &params, &root, &leaf_g).unwrap().enforce_equal(&Boolean::Constant(true)).unwrap();
+        let binary_constraints = cs.num_constraints() - before;
+        assert!(cs.is_satisfied().unwrap());
+
+        // and with the arity-4 tree
+        let check_quad = |leaf_index: usize| {
+            let cs = ConstraintSystem::<F>::new_ref();
+            let params = CRHParametersVar::<F>::new_constant(cs.clone(), quad_hash_params.clone()).unwrap();
+            let root = FpVar::new_input(cs.clone(), || Ok(quad_tree.root())).unwrap();
+            let leaf_g = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(leaves[voter_pos].clone())).unwrap();
+            let leaf_pos = UInt32::new_witness(cs.clone(), || Ok(leaf_index as u32)).unwrap().to_bits_le();
+            let before = cs.num_constraints();
+            let cw = QuadPathVar::new_witness(cs.clone(), || Ok(quad_proof.clone())).unwrap();
+            cw.verify_membership(&params, &root, &leaf_g, &leaf_pos).unwrap().enforce_equal(&Boolean::Constant(true)).unwrap();
+            (cs.num_constraints() - before, cs.is_satisfied().unwrap())
+        };
+        let (quad_constraints, is_satisfied) = check_quad(voter_pos);
+        assert!(is_satisfied);
+        assert!(!check_quad(voter_pos ^ 2).1);
+
+        println!("Membership constraints, binary tree: {}", binary_constraints);
+        println!("Membership constraints, arity-4 tree: {}", quad_constraints);
+        assert!(quad_constraints < binary_constraints);
+    }
+
+    #[test]
+    fn test_ballot_builder() {
+        assert!(Ballot::Abstain.to_vote_m::<F>(BallotMode::Plurality, None, 2, 2).is_err());
+        assert!(Ballot::Single(2).to_vote_m::<F>(BallotMode::Plurality, None, 2, 4).is_err());
+        assert!(Ballot::Single(0).to_vote_m::<F>(BallotMode::Plurality, None, 3, 2).is_err());
+        assert!(Ballot::Single(0).to_vote_m::<F>(BallotMode::Approval, Some(1), 2, 2).is_err());
+        assert!(Ballot::Approval(vec![0, 1]).to_vote_m::<F>(BallotMode::Approval, None, 2, 2).is_err());
+        assert!(Ballot::Approval(vec![0, 1]).to_vote_m::<F>(BallotMode::Approval, Some(1), 2, 2).is_err());
+        assert!(Ballot::Approval(vec![1, 1]).to_vote_m::<F>(BallotMode::Approval, Some(2), 2, 2).is_err());
+        assert!(Ballot::Points(vec![8, 0]).to_vote_m::<F>(BallotMode::Cumulative { bits: 3 }, Some(8), 2, 2).is_err());
+        assert!(Ballot::Points(vec![3, 3]).to_vote_m::<F>(BallotMode::Cumulative { bits: 3 }, Some(5), 2, 2).is_err());
+        assert!(Ballot::Points(vec![1, 1, 1]).to_vote_m::<F>(BallotMode::Cumulative { bits: 3 }, Some(3), 2, 3).is_err());
+        assert!(Ballot::Points(vec![3, 3]).to_vote_m::<F>(BallotMode::Quadratic { bits: 3 }, Some(17), 2, 2).is_err());
+        assert!(Ballot::Points(vec![3, 3]).to_vote_m::<F>(BallotMode::Quadratic { bits: 3 }, Some(18), 2, 2).is_ok());
+        assert!(Ballot::Points(vec![2, 2]).to_vote_m::<F>(BallotMode::Weighted { bits: 2 }, Some(3), 2, 2).is_err());
+        assert!(Ballot::Points(vec![2, 1]).to_vote_m::<F>(BallotMode::Weighted { bits: 2 }, None, 2, 2).is_err());
+        assert!(Ballot::Points(vec![1, 1]).to_vote_m::<F>(BallotMode::Quadratic { bits: 33 }, Some(2), 2, 2).is_err());
+        assert!(Ballot::Points(vec![1, 1]).to_vote_m::<F>(BallotMode::Cumulative { bits: 64 }, Some(2), 2, 2).is_err());
+        assert!(Ballot::Points(vec![1 << 31, 1]).to_vote_m::<F>(BallotMode::Cumulative { bits: 32 }, Some((1 << 31) + 1), 2, 2).is_ok());
+        assert!(Ballot::Single(2).to_choice::<F>(BallotMode::Indexed, 2).is_err());
+        assert!(Ballot::Single(1).to_choice::<F>(BallotMode::Plurality, 2).is_err());
+        assert_eq!(Ballot::Single(1).to_choice::<F>(BallotMode::Indexed, 2).unwrap(), F::from(1u64));
+        assert!(Ballot::Ranking(vec![0, 0]).to_vote_m::<F>(BallotMode::Ranked, None, 2, 2).is_err());
+        assert!(Ballot::Ranking(vec![2]).to_vote_m::<F>(BallotMode::Ranked, None, 2, 3).is_err());
+        assert_eq!(
+            Ballot::Ranking(vec![1, 2, 0]).to_vote_m::<F>(BallotMode::Ranked, None, 3, 3).unwrap(),
+            vec![F::from(1u64), F::from(3u64), F::from(2u64)]
+        );
+        assert_eq!(
+            Ballot::Points(vec![3, 2]).to_vote_m::<F>(BallotMode::Cumulative { bits: 3 }, Some(5), 2, 3).unwrap(),
+            vec![F::from(3u64), F::from(2u64), F::from(0u64)]
+        );
+        assert_eq!(
+            Ballot::Approval(vec![2, 0]).to_vote_m::<F>(BallotMode::Approval, Some(2), 3, 3).unwrap(),
+            vec![F::from(1u64), F::from(0u64), F::from(1u64)]
+        );
+        assert_eq!(
+            Ballot::Single(1).to_vote_m::<F>(BallotMode::Abstainable, None, 2, 3).unwrap(),
+            vec![F::from(0u64), F::from(1u64), F::from(0u64)]
+        );
+    }
+
+    #[test]
+    fn test_voting_wrong_g_r() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let mut test_circuit = make_mocking_circuit();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        let mut g_r = test_circuit.instance.contests[0].g_r.clone().unwrap();
+        g_r.swap(0, 1);
+        test_circuit.instance.contests[0].g_r = Some(g_r);
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_election_scope() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let test_circuit = make_mocking_circuit();
+
+        // the same voter in another deployment gets an unrelated nullifier
+        let other_addr = from_address_to_base_field("0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512").unwrap();
+        let contests = vec![contest(BallotMode::Plurality, None, 2, 2)];
+        let other_circuit = make_mocking_circuit_with(contests, vec![Ballot::Single(1)], ElectionConfig { contract_addr: other_addr, ..election() }).unwrap();
+        assert_ne!(other_circuit.instance.sn, test_circuit.instance.sn);
+
+        // a proof cannot be replayed for another deployment
+        let mut wrong_circuit = test_circuit;
+        wrong_circuit.instance.contract_addr = Some(other_addr);
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        assert_eq!(from_address_to_base_field("0x0000000000000000000000000000000000000102").unwrap(), F::from(258u64));
+        assert!(from_address_to_base_field("0x1234").is_err());
+    }
+
+    #[test]
+    fn test_voting_commitment() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        use crate::circuits::voting::registry::identity_commitment;
+        use crate::circuits::voting::poseidon_params::get_poseidon_params;
+        use ark_ec::AffineRepr;
+        let hash_params = get_poseidon_params::<F>();
+        let test_circuit = make_mocking_circuit();
+
+        // the same voter registers unrelated commitments for two elections
+        let user = get_user(1).unwrap();
+        let parsed_user = parse_user(&user).unwrap();
+        let other_addr = from_address_to_base_field("0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512").unwrap();
+        let contests = vec![contest(BallotMode::Plurality, None, 2, 2)];
+        let other_circuit = make_mocking_circuit_with(contests, vec![Ballot::Single(1)], ElectionConfig { contract_addr: other_addr, ..election() }).unwrap();
+        let (pk_x, pk_y) = parsed_user.pk.xy().unwrap();
+        let cm = identity_commitment(&hash_params, *pk_x, *pk_y, test_circuit.witness.salt.unwrap()).unwrap();
+        let other_cm = identity_commitment(&hash_params, *pk_x, *pk_y, other_circuit.witness.salt.unwrap()).unwrap();
+        assert_ne!(cm, other_cm);
+        assert_ne!(other_circuit.instance.root, test_circuit.instance.root);
+
+        // the commitment only opens with its own salt
+        let mut wrong_circuit = test_circuit;
+        wrong_circuit.witness.salt = other_circuit.witness.salt;
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_voting_quad_tree() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let contests = vec![contest(BallotMode::Plurality, None, 2, 2)];
+        let generate = |tree_height: u64, quad_tree: bool| make_mocking_circuit_with(contests.clone(), vec![Ballot::Single(1)], ElectionConfig { tree_height, quad_tree, ..election() });
+
+        let test_circuit = generate(10, true).unwrap();
+        assert!(test_circuit.witness.tree_proof.is_none());
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // the voting proof is cheaper over the same 1024 voters
+        let binary_cs = ConstraintSystem::<F>::new_ref();
+        generate(10, false).unwrap().generate_constraints(binary_cs.clone()).unwrap();
+        println!("Number of constraints: binary {} vs arity-4 {}", binary_cs.num_constraints(), cs.num_constraints());
+        assert!(cs.num_constraints() < binary_cs.num_constraints());
+
+        // against another root
+        let mut wrong_circuit = test_circuit;
+        wrong_circuit.instance.root = generate(10, false).unwrap().instance.root;
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // 2^9 leaves do not make an arity-4 tree
+        assert!(generate(9, true).is_err());
+    }
+
+    #[test]
+    fn test_circom_poseidon() {
+        use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+        use crate::circuits::voting::semaphore::CircomPoseidon;
+        use std::str::FromStr;
+        let poseidon = CircomPoseidon::<F>::new();
+
+        // test vectors of circomlib's poseidon
+        assert_eq!(poseidon.hash(&[F::from(1u64)]).unwrap(), F::from_str("18586133768512220936620570745912940619677854269274689475585506675881198879027").unwrap());
+        assert_eq!(poseidon.hash(&[F::from(1u64), F::from(2u64)]).unwrap(), F::from_str("7853200120776062878684798364095072458815029376092732009249414926327459813530").unwrap());
+        assert!(poseidon.hash(&[]).is_err());
+
+        let cs = ConstraintSystem::<F>::new_ref();
+        let inputs = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(vec![F::from(1u64), F::from(2u64)])).unwrap();
+        let hash = poseidon.hash_gadget(cs.clone(), &inputs).unwrap();
+        assert_eq!(hash.value().unwrap(), poseidon.hash(&[F::from(1u64), F::from(2u64)]).unwrap());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_semaphore_group() {
+        use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, fields::fp::FpVar, R1CSVar};
+        use crate::circuits::voting::semaphore::{CircomPoseidon, SemaphoreGroup, SemaphoreIdentity, SemaphorePathVar};
+        let poseidon = CircomPoseidon::<F>::new();
+
+        let identities = (0..5u64).map(|i| SemaphoreIdentity { trapdoor: F::from(2 * i + 1), nullifier: F::from(2 * i + 2) }).collect::<Vec<_>>();
+        let mut group = SemaphoreGroup::new(4, F::from(0u64)).unwrap();
+        let empty_root = group.root().unwrap();
+        for identity in identities.iter() {
+            group.add_member(identity.commitment(&poseidon).unwrap()).unwrap();
+        }
+        assert_ne!(group.root().unwrap(), empty_root);
+        assert_eq!(group.members().len(), 5);
+        assert!(group.generate_proof(5).is_err());
+
+        // the root of a full tree of depth 4 over the members and zero leaves
+        let mut level = group.members().to_vec();
+        level.resize(16, F::from(0u64));
+        while level.len() > 1 {
+            level = level.chunks(2).map(|pair| poseidon.hash(pair).unwrap()).collect();
+        }
+        assert_eq!(level[0], group.root().unwrap());
+
+        for (i, identity) in identities.iter().enumerate() {
+            let path = group.generate_proof(i).unwrap();
+            let commitment = identity.commitment(&poseidon).unwrap();
+            assert_eq!(path.root(&poseidon, commitment).unwrap(), group.root().unwrap());
+
+            let cs = ConstraintSystem::<F>::new_ref();
+            let leaf = FpVar::<F>::new_witness(cs.clone(), || Ok(commitment)).unwrap();
+            let leaf_pos = Vec::<Boolean<F>>::new_witness(cs.clone(), || Ok((0..4).map(|j| (i >> j) & 1 == 1).collect::<Vec<_>>())).unwrap();
+            let path_var = SemaphorePathVar::new_witness(cs.clone(), || Ok(path)).unwrap();
+            assert_eq!(path_var.root(cs.clone(), &poseidon, &leaf, &leaf_pos).unwrap().value().unwrap(), group.root().unwrap());
+        }
+
+        let mut full_group = SemaphoreGroup::new(1, F::from(0u64)).unwrap();
+        full_group.add_member(F::from(1u64)).unwrap();
+        full_group.add_member(F::from(2u64)).unwrap();
+        assert!(full_group.add_member(F::from(3u64)).is_err());
+    }
+
+    #[test]
+    fn test_voting_semaphore() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let contests = vec![contest(BallotMode::Plurality, None, 2, 2)];
+        let generate = |contests: Vec<Contest>, revoked_voters: Option<Vec<u64>>| make_mocking_circuit_with(contests, vec![Ballot::Single(1)], ElectionConfig { revoked_voters, semaphore: true, ..election() });
+
+        let test_circuit = generate(contests.clone(), Some(vec![0])).unwrap();
+        assert!(test_circuit.witness.sk.is_none());
+        assert_ne!(test_circuit.instance.root, make_mocking_circuit().instance.root);
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        println!("Number of constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+
+        // another identity is not in the group
+        let mut wrong_circuit = test_circuit;
+        wrong_circuit.witness.identity_trapdoor = Some(wrong_circuit.witness.identity_trapdoor.unwrap() + F::from(1u64));
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // revoked identities and weights do not apply
+        assert!(generate(contests, Some(vec![1])).is_err());
+        let weighted = vec![contest(BallotMode::Weighted { bits: 2 }, None, 2, 2)];
+        assert!(generate(weighted, None).is_err());
+    }
+
+    #[test]
+    fn test_voting_relayer() {
+        use crate::circuits::voting::parser::from_uint_to_base_field;
+        let contests = vec![contest(BallotMode::Plurality, None, 2, 2)];
+        let submitter = from_address_to_base_field("0x70997970C51812dc3A010C7d01b50e0d17dc79C8").unwrap();
+        let fee = from_uint_to_base_field("1000000000000000").unwrap();
+        let test_circuit = make_mocking_circuit_with(contests, vec![Ballot::Single(1)], ElectionConfig { relayer: Some((submitter, fee)), ..election() }).unwrap();
+        let (pk, _, pvk) = voting_setup(test_circuit.clone());
+        let proof = voting_prove(pk, test_circuit.clone());
+
+        let image = test_circuit.instance.public_inputs().unwrap();
+        assert!(Groth16::<Bn254>::verify_with_processed_vk(&pvk, &image, &proof).unwrap());
+
+        // a front-runner cannot claim the proof, nor change the fee
+        let mut stolen = image.clone();
+        stolen[3] = from_address_to_base_field("0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC").unwrap();
+        assert!(!Groth16::<Bn254>::verify_with_processed_vk(&pvk, &stolen, &proof).unwrap());
+        let mut stolen = image;
+        stolen[4] = F::from(0u64);
+        assert!(!Groth16::<Bn254>::verify_with_processed_vk(&pvk, &stolen, &proof).unwrap());
+
+        assert_eq!(from_uint_to_base_field("258").unwrap(), F::from(258u64));
+        assert!(from_uint_to_base_field("21888242871839275222246405745257275088548364400416034343698204186575808495617").is_err());
+        assert!(from_uint_to_base_field("0x10").is_err());
+    }
+
+    #[test]
+    fn test_voting_wrong_sn() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let mut test_circuit = make_mocking_circuit();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.instance.sn = Some(test_circuit.instance.sn.unwrap() + F::from(1u64));
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+
+    /// `PseVoting.inputsLength` for a deployment with these options.
+    fn contract_inputs_length(relayed: bool, revoting: bool, revocation: bool, ballot_bounds: &[bool], candidate_limit: usize) -> usize {
+        let mut length = 6 + 2 * relayed as usize + revoting as usize + revocation as usize;
+        for ballot_bound in ballot_bounds.iter() {
+            length += 1 + 4 * candidate_limit + *ballot_bound as usize;
+        }
+        length
+    }
+
+    #[test]
+    fn test_voting_public_inputs() {
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        use crate::circuits::voting::parser::from_uint_to_base_field;
+        let plurality = contest(BallotMode::Plurality, None, 2, 2);
+        let approval = contest(BallotMode::Approval, Some(1), 2, 2);
+        let relayer = (from_address_to_base_field("0x70997970C51812dc3A010C7d01b50e0d17dc79C8").unwrap(), from_uint_to_base_field("1000").unwrap());
+
+        let circuits = vec![
+            (make_mocking_circuit(), contract_inputs_length(false, false, false, &[false], 2)),
+            (make_mocking_circuit_with(vec![plurality], vec![Ballot::Single(1)], ElectionConfig { relayer: Some(relayer), ..election() }).unwrap(), contract_inputs_length(true, false, false, &[false], 2)),
+            (make_mocking_circuit_with(vec![plurality, approval], vec![Ballot::Single(1), Ballot::Approval(vec![0])], ElectionConfig { version: Some(1), revoked_voters: Some(vec![0]), relayer: Some(relayer), ..election() }).unwrap(), contract_inputs_length(true, true, true, &[false, true], 2)),
+        ];
+        assert_eq!(circuits[0].1, 15);
+
+        for (test_circuit, length) in circuits {
+            let inputs = test_circuit.instance.public_inputs().unwrap();
+            let cs = ConstraintSystem::<F>::new_ref();
+
+            test_circuit.generate_constraints(cs.clone()).unwrap();
+            assert!(cs.is_satisfied().unwrap());
+            assert_eq!(cs.borrow().unwrap().instance_assignment[1..], inputs[..]);
+            assert_eq!(inputs.len(), length);
+        }
+    }
+
+
+    const CONTRACT_FIXTURE: &str = "../Voting-Contract/test/fixtures/voting_inputs.json";
+
+    /// The public inputs of a satisfied voting circuit as the PseVoting tests take them, with the
+    /// number of leading inputs that do not depend on the randomness of g_r and vote_cm.
+    fn contract_round(contract: &str, test_circuit: VotingCircuit<C, GG>) -> (serde_json::Value, usize) {
+        use ark_ff::PrimeField;
+        use ark_relations::r1cs::ConstraintSynthesizer;
+        let (g, instance) = (test_circuit.g, test_circuit.instance.clone());
+        let inputs = instance.public_inputs().unwrap();
+        let cs = ConstraintSystem::<F>::new_ref();
+
+        test_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        let contest = &instance.contests[instance.contests.len() - 1];
+        let num_of_fixed = inputs.len() - 2 * (contest.g_r.as_ref().unwrap().len() + contest.vote_cm.as_ref().unwrap().len());
+        // Display prints a zero field element as an empty string, so go through the integer
+        let dec = |x: &F| x.into_bigint().to_string();
+        let point = |p: &ark_ed_on_bn254::EdwardsAffine| [dec(&p.x), dec(&p.y)];
+        // the voteList of submitVote, one (g_r, vote_cm) pair per candidate
+        let num_of_candidates: usize = dec(&contest.num_of_candidates.unwrap()).parse().unwrap();
+        let (g_r, vote_cm) = (contest.g_r.as_ref().unwrap(), contest.vote_cm.as_ref().unwrap());
+        let ck = [point(&g), point(&instance.h.unwrap())].concat();
+        let votes = (0..num_of_candidates).map(|i| [point(&g_r[i]), point(&vote_cm[i])]).collect::<Vec<_>>();
+        let mut round = serde_json::json!({
+            "contract": contract,
+            "votingRound": dec(&instance.voting_round.unwrap()),
+            "sn": dec(&instance.sn.unwrap()),
+            "root": dec(&instance.root.unwrap()),
+            "inputs": inputs.iter().map(dec).collect::<Vec<String>>(),
+            "ck": ck,
+            "votes": votes,
+        });
+        if let Some(prev_sn) = instance.prev_sn {
+            round["prevSn"] = dec(&prev_sn).into();
+        }
+        if let Some(revocation_root) = instance.revocation_root {
+            round["revocationRoot"] = dec(&revocation_root).into();
+        }
+        (round, num_of_fixed)
+    }
+
+    /// Checks the inputs the PseVoting tests in Voting-Contract take against real voting circuits,
+    /// and rewrites them with UPDATE_CONTRACT_FIXTURE set. The contracts are the first two
+    /// deployments of the default hardhat account, the relayed one with every optional input.
+    #[test]
+    fn test_voting_contract_inputs() {
+        use crate::circuits::voting::parser::from_uint_to_base_field;
+        const RELAYED_CONTRACT_ADDR: &str = "0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512";
+        const SUBMITTER: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+        const FEE: &str = "1000000000000000";
+        const BOUNDED_CONTRACT_ADDR: &str = "0x9fE46736679d2D9a65F0992F2272dE9f3c7fa6e0";
+        let contests = vec![contest(BallotMode::Plurality, None, 2, 2)];
+        let relayer = (from_address_to_base_field(SUBMITTER).unwrap(), from_uint_to_base_field(FEE).unwrap());
+        // the first ballot of the voter and the re-vote that replaces it
+        let relayed_circuit = |version| {
+            let config = ElectionConfig { contract_addr: from_address_to_base_field(RELAYED_CONTRACT_ADDR).unwrap(), version: Some(version), revoked_voters: Some(vec![0]), relayer: Some(relayer), ..election() };
+            make_mocking_circuit_with(contests.clone(), vec![Ballot::Single(1)], config).unwrap()
+        };
+
+        let mut rounds = [
+            ("plain", contract_round(CONTRACT_ADDR, make_mocking_circuit())),
+            ("relayedFirst", contract_round(RELAYED_CONTRACT_ADDR, relayed_circuit(0))),
+            ("relayed", contract_round(RELAYED_CONTRACT_ADDR, relayed_circuit(1))),
+            // approval of at most one candidate
+            ("bounded", contract_round(BOUNDED_CONTRACT_ADDR, make_mocking_circuit_with(vec![contest(BallotMode::Approval, Some(1), 2, 2)], vec![Ballot::Approval(vec![1])], ElectionConfig { contract_addr: from_address_to_base_field(BOUNDED_CONTRACT_ADDR).unwrap(), ..election() }).unwrap())),
+        ];
+        assert_eq!(rounds[2].1.0["prevSn"], rounds[1].1.0["sn"]);
+        for (_, (round, _)) in rounds[1..3].iter_mut() {
+            round["submitter"] = SUBMITTER.into();
+            round["fee"] = FEE.into();
+        }
+
+        let fixture = serde_json::Value::Object(rounds.iter().map(|(name, (round, _))| (name.to_string(), round.clone())).collect());
+        if std::env::var("UPDATE_CONTRACT_FIXTURE").is_ok() {
+            std::fs::write(CONTRACT_FIXTURE, serde_json::to_string_pretty(&fixture).unwrap() + "\n").unwrap();
+        }
+
+        let committed: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(CONTRACT_FIXTURE).unwrap()).unwrap();
+        for (name, (round, num_of_fixed)) in rounds.iter() {
+            let (inputs, committed_inputs) = (round["inputs"].as_array().unwrap(), committed[name]["inputs"].as_array().unwrap());
+            assert_eq!(committed_inputs.len(), inputs.len());
+            assert_eq!(committed_inputs[..*num_of_fixed], inputs[..*num_of_fixed]);
+            for key in ["contract", "votingRound", "sn", "root", "prevSn", "revocationRoot", "submitter", "fee", "ck"] {
+                assert_eq!(committed[name][key], round[key]);
+            }
+        }
+    }
+
+
+    #[test]
+    fn test_voting_decrypt() {
+        use crate::circuits::tally::tally_circuit::decrypt_count;
+        use crate::circuits::voting::parser::get_x;
+        let test_circuit = make_mocking_circuit();
+        let x = get_x().unwrap();
+
+        let g_r = test_circuit.instance.contests[0].g_r.clone().unwrap();
+        let vote_cm = test_circuit.instance.contests[0].vote_cm.clone().unwrap();
+        let vote_m = test_circuit.witness.contests[0].vote_m.clone().unwrap();
+        for i in 0..vote_m.len() {
+            let m = decrypt_count::<C>(test_circuit.g, x, g_r[i], vote_cm[i], 1).unwrap();
+            assert_eq!(F::from(m), vote_m[i]);
+        }
+    }
+
 
     #[test]
     fn test_voting_setup() {
@@ -63,12 +1151,8 @@ mod test {
 
     #[test]
     fn test_voting_prove() {
-        use crate::circuits::voting::key_utils::{load_pk, load_vk};
         let test_circuit = make_mocking_circuit();
-        println!("loading ...");
-        let pk = load_pk();
-        let vk = load_vk();
-        println!("loaded !");
+        let (pk, vk, pvk) = voting_setup(test_circuit.clone());
         println!("vk.alpha_g1: {:?}\n", vk.alpha_g1);
         println!("vk.beta_g2: {:?}\n", vk.beta_g2);
         println!("vk.gamma_g2: {:?}\n", vk.gamma_g2);
@@ -76,25 +1160,7 @@ mod test {
         println!("vk.gamma_abc_g1: {:?}\n", vk.gamma_abc_g1);
         println!("len(vk.gamma_abc_g1): {:?}\n", vk.gamma_abc_g1.len());
     
-        let pvk = Groth16::<Bn254>::process_vk(&vk).unwrap();
-        let mut image: Vec<_> = vec![];
-
-        image.append(&mut vec![
-            test_circuit.instance.voting_round.clone().unwrap(),
-            test_circuit.instance.root.clone().unwrap(),
-        ]);
-
-        for i in test_circuit.instance.vote_cm.clone().unwrap() {
-            if i.is_zero() {
-                let zero = F::ZERO;
-                image.push(zero); // TODO: num_of_candidate가 candidate_limit보다 작을 경우, 에러가 발생함.
-                image.push(zero);
-            }
-            else {
-                image.push(*i.clone().x().unwrap());
-                image.push(*i.clone().y().unwrap());
-            }
-        }
+        let image = test_circuit.instance.public_inputs().unwrap();
 
         println!("image: {:?}", image);
         
@@ -1,24 +1,108 @@
 use std::marker::PhantomData;
 use ark_ec::CurveGroup;
-use ark_ff::{Field, PrimeField};
+use ark_ff::{Field, PrimeField, ToConstraintField};
 use ark_r1cs_std::{prelude::*, fields::fp::FpVar};
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use ark_crypto_primitives::{
-    crh::{poseidon::{constraints::{CRHGadget, CRHParametersVar}, CRH}, CRHScheme, CRHSchemeGadget}, merkle_tree::{self, constraints::PathVar, MerkleTree}, sponge::{poseidon::PoseidonConfig, Absorb}
+    crh::{poseidon::{constraints::{CRHGadget, CRHParametersVar}, CRH}, CRHScheme, CRHSchemeGadget}, merkle_tree::{self, constraints::PathVar}, sponge::{poseidon::PoseidonConfig, Absorb}
 };
 use ark_std::Zero;
 use rand::thread_rng;
-use crate::circuits::voting::merkle_tree::{MerkleTreeParams, MerkleTreeParamsVar};
-use crate::circuits::voting::MockingCircuit;
-use crate::circuits::voting::poseidon_params::get_poseidon_params;
+use crate::circuits::voting::merkle_tree::{IncrementalMerkleTree, MerkleTreeParams, MerkleTreeParamsVar};
+use crate::circuits::voting::quad_merkle_tree::{QuadMerkleTree, QuadPath, QuadPathVar};
+use crate::circuits::voting::registry::{empty_voter_leaf, identity_commitment, key_commitment_gadget, registered_voter_leaves, voter_leaves};
+use crate::circuits::voting::revocation::IndexedMerkleTree;
+use crate::circuits::voting::semaphore::{identity_commitment_gadget, CircomPoseidon, SemaphoreGroup, SemaphoreIdentity, SemaphorePath, SemaphorePathVar};
+use crate::circuits::voting::gadgets::is_less_than;
+use crate::circuits::voting::ballot::{Ballot, BallotMode, Contest};
+use crate::circuits::voting::{ElectionConfig, MockingCircuit};
+use crate::circuits::voting::poseidon_params::{get_poseidon_params, get_quad_poseidon_params};
+use crate::Error;
 
 pub type ConstraintF<C> = <<C as CurveGroup>::BaseField as Field>::BasePrimeField;
 
+/// Tag hashed into the election identifier, so that nullifiers differ from any other
+/// Poseidon output over the same key.
+pub fn nullifier_domain<F: PrimeField>() -> F {
+    F::from_le_bytes_mod_order(b"PSE-Voting/nullifier")
+}
+
+/// Tag hashed into the registration identifier, so that registration nullifiers cannot be
+/// matched with the serial numbers of ballots.
+pub fn registration_domain<F: PrimeField>() -> F {
+    F::from_le_bytes_mod_order(b"PSE-Voting/registration")
+}
+
+/// Tag for deriving the registration salt of mock voters from their key.
+pub fn salt_domain<F: PrimeField>() -> F {
+    F::from_le_bytes_mod_order(b"PSE-Voting/salt")
+}
+
+#[derive(Clone, Debug)]
+pub struct ContestInstance<C: CurveGroup> {
+    pub num_of_candidates: Option<C::BaseField>,
+    pub ballot_bound: Option<C::BaseField>,  // only for ballot modes that take it (k of approval)
+    pub g_r: Option<Vec<C::Affine>>,
+    pub vote_cm: Option<Vec<C::Affine>>,
+}
+
 #[derive(Clone, Debug)]
 pub struct VotingInstance<C: CurveGroup> {
     pub voting_round: Option<C::BaseField>,
+    pub sn: Option<C::BaseField>,
+    pub contract_addr: Option<C::BaseField>,  // address of the PseVoting deployment
+    pub submitter: Option<C::BaseField>,  // only when bound to a relayer, who must be msg.sender
+    pub fee: Option<C::BaseField>,  // only when bound to a relayer
+    pub prev_sn: Option<C::BaseField>,  // only in re-voting mode, zero for the first ballot; shows which ballot is replaced
     pub root: Option<C::BaseField>,
-    pub vote_cm: Option<Vec<C::Affine>>,
+    pub revocation_root: Option<C::BaseField>,  // only with a revocation list
+    pub h: Option<C::Affine>,
+    pub contests: Vec<ContestInstance<C>>,
+}
+
+impl<C: CurveGroup> VotingInstance<C>
+where
+    C::Affine: ToConstraintField<C::BaseField>,
+{
+    /// The public inputs in the order the circuit allocates them, which is also the layout
+    /// `PseVoting` reads: voting_round, sn, contract_addr, [submitter, fee], [prev_sn], root,
+    /// [revocation_root], h, then per contest num_of_candidates, [ballot_bound], g_r, vote_cm.
+    /// Points take two inputs, x and y.
+    pub fn public_inputs(&self) -> Result<Vec<C::BaseField>, Error> {
+        let missing = |name: &str| format!("{} is not assigned", name);
+        let point = |p: &C::Affine| p.to_field_elements().ok_or("a point has no field elements".to_string());
+
+        let mut inputs = vec![
+            self.voting_round.ok_or(missing("voting_round"))?,
+            self.sn.ok_or(missing("sn"))?,
+            self.contract_addr.ok_or(missing("contract_addr"))?,
+        ];
+        match (self.submitter, self.fee) {
+            (Some(submitter), Some(fee)) => inputs.extend([submitter, fee]),
+            (None, None) => {}
+            _ => return Err("submitter and fee come together".into()),
+        }
+        inputs.extend(self.prev_sn);
+        inputs.push(self.root.ok_or(missing("root"))?);
+        inputs.extend(self.revocation_root);
+        inputs.extend(point(self.h.as_ref().ok_or(missing("h"))?)?);
+
+        for contest in self.contests.iter() {
+            inputs.push(contest.num_of_candidates.ok_or(missing("num_of_candidates"))?);
+            inputs.extend(contest.ballot_bound);
+            for p in contest.g_r.as_ref().ok_or(missing("g_r"))?.iter().chain(contest.vote_cm.as_ref().ok_or(missing("vote_cm"))?) {
+                inputs.extend(point(p)?);
+            }
+        }
+        Ok(inputs)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ContestWitness<C: CurveGroup> {
+    pub choice: Option<C::BaseField>,  // only in indexed mode, instead of vote_m
+    pub vote_m: Option<Vec<C::BaseField>>,
+    pub vote_r: Option<Vec<C::BaseField>>,
 }
 
 #[derive(Clone, Debug)]
@@ -27,16 +111,35 @@ where
     C::BaseField: PrimeField + Absorb,
 {
     pub sk: Option<C::BaseField>,
+    pub version: Option<C::BaseField>,  // only in re-voting mode
     pub pk: Option<C::Affine>,
     pub addr: Option<C::BaseField>,
-    pub vote_m: Option<Vec<C::BaseField>>,
-    pub vote_r: Option<Vec<C::BaseField>>,
-    pub sn: Option<C::BaseField>,
+    pub salt: Option<C::BaseField>,  // blinds the commitment registered for this election
+    pub identity_trapdoor: Option<C::BaseField>,  // only with a Semaphore identity, which replaces sk, pk, addr and salt
+    pub identity_nullifier: Option<C::BaseField>,
+    pub weight: Option<C::BaseField>,  // only if a contest is weighted
+    pub contests: Vec<ContestWitness<C>>,
     pub leaf_pos: Option<u32>,
     pub tree_proof: Option<merkle_tree::Path<MerkleTreeParams<C::BaseField>>>,
+    pub semaphore_proof: Option<SemaphorePath<C::BaseField>>,  // instead of tree_proof with a Semaphore identity
+    pub quad_tree_proof: Option<QuadPath<C::BaseField>>,  // instead of tree_proof in an arity-4 voter tree
+    pub low_leaf: Option<Vec<C::BaseField>>,  // only with a revocation list, [value, next_index, next_value]
+    pub low_leaf_pos: Option<u32>,
+    pub revocation_proof: Option<merkle_tree::Path<MerkleTreeParams<C::BaseField>>>,
 }
 
 
+/// The optional parts of the voting circuit. Each one changes the public inputs or the voter
+/// tree, so a proving key is made for one set of options. The default is a plain election.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VotingOptions {
+    pub revoting: bool,  // a later ballot of the voter replaces the one with sn == prev_sn
+    pub revocation: bool,  // addr must not be in the revocation tree
+    pub relayer: bool,  // the proof is bound to a submitter and fee
+    pub semaphore: bool,  // the voter tree is a Semaphore group of identity commitments
+    pub quad_tree: bool,  // the voter tree has arity 4, see QuadMerkleTree
+}
+
 #[derive(Clone, Debug)]
 pub struct VotingCircuit<C: CurveGroup, GG: CurveVar<C, ConstraintF<C>>>
 where 
@@ -46,8 +149,9 @@ where
     for<'a> &'a GG: GroupOpsBounds<'a, C, GG>,
 {
     pub g: C::Affine,
-    pub ck: Vec<C::Affine>,
     pub hash_params: PoseidonConfig<C::BaseField>,
+    pub ballot_modes: Vec<BallotMode>,  // one per contest
+    pub options: VotingOptions,
     pub instance: VotingInstance<C>,
     pub witness: VotingWitness<C>,
     _curve: PhantomData<GG>,
@@ -62,15 +166,17 @@ where
 {
     pub fn new(
         g: C::Affine,
-        ck: Vec<C::Affine>,
         hash_params: PoseidonConfig<C::BaseField>,
+        ballot_modes: Vec<BallotMode>,
+        options: VotingOptions,
         instance: VotingInstance<C>,
         witness: VotingWitness<C>,
     ) -> Self {
         Self {
             g,
-            ck,
             hash_params,
+            ballot_modes,
+            options,
             instance,
             witness,
             _curve: PhantomData,
@@ -89,81 +195,233 @@ where
 {
     fn generate_constraints(self, cs: ConstraintSystemRef<C::BaseField>) -> ark_relations::r1cs::Result<()> {
         let g = GG::new_constant(cs.clone(), self.g)?;
-        let ck = Vec::<GG>::new_constant(cs.clone(), self.ck)?;
         let hash_params = CRHParametersVar::<C::BaseField>::new_constant(cs.clone(), self.hash_params)?;
+        let poseidon = CircomPoseidon::<C::BaseField>::new();
         let zero = FpVar::<C::BaseField>::zero();
-        let one = FpVar::<C::BaseField>::one();
 
         // instance
         let voting_round = FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.voting_round.ok_or(SynthesisError::AssignmentMissing))?;
+        let sn = FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.sn.ok_or(SynthesisError::AssignmentMissing))?;
+        let contract_addr = FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.contract_addr.ok_or(SynthesisError::AssignmentMissing))?;
+        let relayer = if self.options.relayer {
+            let submitter = FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.submitter.ok_or(SynthesisError::AssignmentMissing))?;
+            let fee = FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.fee.ok_or(SynthesisError::AssignmentMissing))?;
+            Some((submitter, fee))
+        } else {
+            None
+        };
+        let prev_sn = if self.options.revoting {
+            Some(FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.prev_sn.ok_or(SynthesisError::AssignmentMissing))?)
+        } else {
+            None
+        };
         let root = FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.root.ok_or(SynthesisError::AssignmentMissing))?;
-        let vote_cm = Vec::<GG>::new_input(cs.clone(), || self.instance.vote_cm.ok_or(SynthesisError::AssignmentMissing))?;
+        let revocation_root = if self.options.revocation {
+            Some(FpVar::<C::BaseField>::new_input(cs.clone(), || self.instance.revocation_root.ok_or(SynthesisError::AssignmentMissing))?)
+        } else {
+            None
+        };
+        let h = GG::new_input(cs.clone(), || self.instance.h.ok_or(SynthesisError::AssignmentMissing))?;
+
+        let mut contests = vec![];
+        for (ballot_mode, contest) in self.ballot_modes.iter().zip(self.instance.contests) {
+            let num_of_candidates = FpVar::<C::BaseField>::new_input(cs.clone(), || contest.num_of_candidates.ok_or(SynthesisError::AssignmentMissing))?;
+            let ballot_bound = if ballot_mode.has_bound() {
+                Some(FpVar::<C::BaseField>::new_input(cs.clone(), || contest.ballot_bound.ok_or(SynthesisError::AssignmentMissing))?)
+            } else {
+                None
+            };
+            let g_r = Vec::<GG>::new_input(cs.clone(), || contest.g_r.ok_or(SynthesisError::AssignmentMissing))?;
+            let vote_cm = Vec::<GG>::new_input(cs.clone(), || contest.vote_cm.ok_or(SynthesisError::AssignmentMissing))?;
+            contests.push((num_of_candidates, ballot_bound, g_r, vote_cm));
+        }
 
 
         // witness
-        let sk = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.sk.ok_or(SynthesisError::AssignmentMissing))?;
-        let pk = GG::new_witness(cs.clone(), || self.witness.pk.ok_or(SynthesisError::AssignmentMissing))?;
-        let addr = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.addr.ok_or(SynthesisError::AssignmentMissing))?;
-        let vote_m = Vec::<FpVar<C::BaseField>>::new_witness(cs.clone(), || self.witness.vote_m.ok_or(SynthesisError::AssignmentMissing))?;
-        let vote_r = Vec::<FpVar<C::BaseField>>::new_witness(cs.clone(), || self.witness.vote_r.ok_or(SynthesisError::AssignmentMissing))?;
-        let sn = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.sn.ok_or(SynthesisError::AssignmentMissing))?;
+        let key = if self.options.semaphore {
+            None
+        } else {
+            let sk = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.sk.ok_or(SynthesisError::AssignmentMissing))?;
+            let pk = GG::new_witness(cs.clone(), || self.witness.pk.ok_or(SynthesisError::AssignmentMissing))?;
+            let addr = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.addr.ok_or(SynthesisError::AssignmentMissing))?;
+            let salt = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.salt.ok_or(SynthesisError::AssignmentMissing))?;
+            Some((sk, pk, addr, salt))
+        };
+        let identity = if self.options.semaphore {
+            let trapdoor = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.identity_trapdoor.ok_or(SynthesisError::AssignmentMissing))?;
+            let nullifier = FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.identity_nullifier.ok_or(SynthesisError::AssignmentMissing))?;
+            Some((trapdoor, nullifier))
+        } else {
+            None
+        };
+        let version = if self.options.revoting {
+            Some(FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.version.ok_or(SynthesisError::AssignmentMissing))?)
+        } else {
+            None
+        };
+        let weight = if self.ballot_modes.iter().any(|ballot_mode| ballot_mode.is_weighted()) {
+            Some(FpVar::<C::BaseField>::new_witness(cs.clone(), || self.witness.weight.ok_or(SynthesisError::AssignmentMissing))?)
+        } else {
+            None
+        };
+        let mut votes = vec![];
+        for (ballot_mode, contest) in self.ballot_modes.iter().zip(self.witness.contests) {
+            let vote_r = Vec::<FpVar<C::BaseField>>::new_witness(cs.clone(), || contest.vote_r.ok_or(SynthesisError::AssignmentMissing))?;
+            let vote_m = if ballot_mode.is_indexed() {
+                let choice = FpVar::<C::BaseField>::new_witness(cs.clone(), || contest.choice.ok_or(SynthesisError::AssignmentMissing))?;
+                BallotMode::one_hot(&choice, vote_r.len())?
+            } else {
+                Vec::<FpVar<C::BaseField>>::new_witness(cs.clone(), || contest.vote_m.ok_or(SynthesisError::AssignmentMissing))?
+            };
+            votes.push((vote_m, vote_r));
+        }
         let leaf_pos = UInt32::new_witness(cs.clone(), || self.witness.leaf_pos.ok_or(SynthesisError::AssignmentMissing))?.to_bits_le();
-        let mut cw = PathVar::<
-                MerkleTreeParams<C::BaseField>,
-                C::BaseField,
-                MerkleTreeParamsVar<C::BaseField>,
-            >::new_witness(cs.clone(), || self.witness.tree_proof.ok_or(SynthesisError::AssignmentMissing))?;
+        let cw = if self.options.semaphore || self.options.quad_tree {
+            None
+        } else {
+            Some(PathVar::<
+                    MerkleTreeParams<C::BaseField>,
+                    C::BaseField,
+                    MerkleTreeParamsVar<C::BaseField>,
+                >::new_witness(cs.clone(), || self.witness.tree_proof.ok_or(SynthesisError::AssignmentMissing))?)
+        };
+        let semaphore_cw = if self.options.semaphore {
+            Some(SemaphorePathVar::new_witness(cs.clone(), || self.witness.semaphore_proof.ok_or(SynthesisError::AssignmentMissing))?)
+        } else {
+            None
+        };
+        let quad_cw = if self.options.quad_tree && !self.options.semaphore {
+            Some(QuadPathVar::new_witness(cs.clone(), || self.witness.quad_tree_proof.ok_or(SynthesisError::AssignmentMissing))?)
+        } else {
+            None
+        };
+        let revocation_witness = if self.options.revocation {
+            let low_leaf = Vec::<FpVar<C::BaseField>>::new_witness(cs.clone(), || self.witness.low_leaf.ok_or(SynthesisError::AssignmentMissing))?;
+            let low_leaf_pos = UInt32::new_witness(cs.clone(), || self.witness.low_leaf_pos.ok_or(SynthesisError::AssignmentMissing))?.to_bits_le();
+            let mut revocation_cw = PathVar::<
+                    MerkleTreeParams<C::BaseField>,
+                    C::BaseField,
+                    MerkleTreeParamsVar<C::BaseField>,
+                >::new_witness(cs.clone(), || self.witness.revocation_proof.ok_or(SynthesisError::AssignmentMissing))?;
+            revocation_cw.set_leaf_position(low_leaf_pos.clone());
+            Some((low_leaf, low_leaf_pos, revocation_cw))
+        } else {
+            None
+        };
 
 
         // Constraints
 
-        // 1. Check pk = g^sk
-        let sk_bits = sk.to_bits_le()?;
-        let pk_computed = g.scalar_mul_le(sk_bits.iter())?;
-        pk_computed.enforce_equal(&pk)?;
-
-
-        // 2. Check addr = CRH(pk)
-        let pk_bits = pk.clone().to_bits_le()?;
-        let pk_x = Boolean::le_bits_to_fp_var(&pk_bits[..pk_bits.len() / 2])?;
-        let pk_y = Boolean::le_bits_to_fp_var(&pk_bits[pk_bits.len() / 2..])?;
-
-        let hash_input = vec![pk_x, pk_y];
-        let addr_computed = CRHGadget::<C::BaseField>::evaluate(&hash_params, &hash_input)?;
-        addr_computed.enforce_equal(&addr)?;
+        let (sk, addr, cm) = if let Some((sk, pk, addr, salt)) = key {
+            // 1-2. Check pk = g^sk, cm = CRH(pk || salt), addr = CRH(pk)
+            let (hash_input, cm) = key_commitment_gadget::<C, GG>(&g, &hash_params, &sk, &pk, &salt)?;
+            let addr_computed = CRHGadget::<C::BaseField>::evaluate(&hash_params, &hash_input)?;
+            addr_computed.enforce_equal(&addr)?;
+            (sk, addr, cm)
+        } else {
+            // 1-2. With a Semaphore identity: cm = Poseidon(Poseidon(identity_nullifier || identity_trapdoor))
+            //      in circomlib's Poseidon. identity_nullifier takes the place of sk, and cm that of addr.
+            let (trapdoor, nullifier) = identity.ok_or(SynthesisError::AssignmentMissing)?;
+            let cm = identity_commitment_gadget(cs.clone(), &poseidon, &trapdoor, &nullifier)?;
+            (nullifier, cm.clone(), cm)
+        };
 
 
-        // 3. Check sn = CRH(sk || voting_round)
-        let hash_input = vec![sk, voting_round];
+        // 3. Check sn = CRH(sk || election_id), election_id = CRH(domain || contract_addr || voting_round)
+        //    (sn = CRH(sk || election_id || version), prev_sn = CRH(sk || election_id || version - 1) or 0 when re-voting)
+        let domain = FpVar::<C::BaseField>::constant(nullifier_domain());
+        let election_id = CRHGadget::<C::BaseField>::evaluate(&hash_params, &[domain, contract_addr, voting_round])?;
+        let mut hash_input = vec![sk.clone(), election_id.clone()];
+        if let Some(version) = version.as_ref() {
+            hash_input.push(version.clone());
+        }
         let sn_computed = CRHGadget::<C::BaseField>::evaluate(&hash_params, &hash_input)?;
         sn_computed.enforce_equal(&sn)?;
 
-        
-        // 4. Check vote_cm = g^mh^r
-        for (i, (vote_m_i, vote_r_i)) in vote_m.iter().zip(vote_r.iter()).enumerate() {
-            let vote_cm_computed = ck[0].scalar_mul_le(vote_m_i.to_bits_le()?.iter())? + ck[1].scalar_mul_le(vote_r_i.to_bits_le()?.iter())?;
-            vote_cm[i].enforce_equal(&vote_cm_computed)?;
+        if let (Some(version), Some(prev_sn)) = (version, prev_sn) {
+            let is_first = version.is_eq(&zero)?;
+            let hash_input = vec![sk, election_id, version - FpVar::one()];
+            let prev_sn_computed = CRHGadget::<C::BaseField>::evaluate(&hash_params, &hash_input)?;
+            is_first.select(&zero, &prev_sn_computed)?.enforce_equal(&prev_sn)?;
         }
 
 
-        // 5. Sum(vote_m) == 1
-        let sum_vote_m = vote_m.iter().fold(FpVar::zero(), |acc, vote| acc + vote);
-        sum_vote_m.enforce_equal(&one)?;
+        // 7. MT.verify(cm (|| weight), path, root) = true
+        //    (in the arity-4 tree, or the root of the Semaphore group holding cm with a Semaphore identity)
+        let mut leaf_g = vec![cm.clone()];
+        if let Some(weight) = weight.clone() {
+            leaf_g.push(weight);
+        }
+        if let Some(quad_cw) = quad_cw {
+            let quad_hash_params = CRHParametersVar::<C::BaseField>::new_constant(cs.clone(), get_quad_poseidon_params())?;
+            let path_check = quad_cw.verify_membership(&quad_hash_params, &root, &leaf_g, &leaf_pos)?;
+            path_check.enforce_equal(&Boolean::Constant(true))?;
+        }
+        if let Some(mut cw) = cw {
+            cw.set_leaf_position(leaf_pos.clone());
+            let path_check = cw.verify_membership(&hash_params, &hash_params, &root, &leaf_g)?;
+            path_check.enforce_equal(&Boolean::Constant(true))?;
+        }
+        if let Some(semaphore_cw) = semaphore_cw {
+            if weight.is_some() {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+            semaphore_cw.root(cs.clone(), &poseidon, &cm, &leaf_pos)?.enforce_equal(&root)?;
+        }
+
+
+        // 9. addr is not revoked: MT.verify(low_leaf, path, revocation_root) = true,
+        //    low_leaf.value < addr < low_leaf.next_value (or low_leaf.next_value == 0),
+        //    low_leaf is the zero leaf at position 0 or a revoked value (low_leaf.value != 0), not an empty slot
+        if let (Some(revocation_root), Some((low_leaf, low_leaf_pos, revocation_cw))) = (revocation_root, revocation_witness) {
+            if low_leaf.len() != 3 {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+            let low_leaf_check = revocation_cw.verify_membership(&hash_params, &hash_params, &revocation_root, &low_leaf)?;
+            low_leaf_check.enforce_equal(&Boolean::Constant(true))?;
+            let is_zero_leaf = Boolean::kary_or(&low_leaf_pos)?.not();
+            is_zero_leaf.or(&low_leaf[0].is_eq(&zero)?.not())?.enforce_equal(&Boolean::Constant(true))?;
+
+            is_less_than(&low_leaf[0], &addr)?.enforce_equal(&Boolean::Constant(true))?;
+            let is_last = low_leaf[2].is_eq(&zero)?;
+            is_last.or(&is_less_than(&addr, &low_leaf[2])?)?.enforce_equal(&Boolean::Constant(true))?;
+        }
+
 
-        
-        // 6. vote_m[i] == 0 or 1
-        for i in vote_m.iter() {
-            let vote_m_i_sq = i * i;
-            let vote_m_sq_minus_vote_m = vote_m_i_sq - i;
-            vote_m_sq_minus_vote_m.enforce_equal(&zero)?;
+        // 10. Bind submitter and fee to the proof
+        if let Some((submitter, fee)) = relayer {
+            let _submitter_sq = submitter.square()?;
+            let _fee_sq = fee.square()?;
         }
-        
 
-        // 7. MT.verify(addr, path, root) = true
-        let leaf_g = vec![addr.clone()];
-        cw.set_leaf_position(leaf_pos.clone());
-        let path_check = cw.verify_membership(&hash_params, &hash_params, &root, &leaf_g)?;
-        path_check.enforce_equal(&Boolean::Constant(true))?;
+
+        for ((ballot_mode, (num_of_candidates, ballot_bound, g_r, vote_cm)), (vote_m, vote_r)) in self.ballot_modes.iter().zip(contests.iter()).zip(votes.iter()) {
+            // 4. Check g_r = g^r, vote_cm = g^m * h^r
+            for (i, (vote_m_i, vote_r_i)) in vote_m.iter().zip(vote_r.iter()).enumerate() {
+                let vote_r_i_bits = vote_r_i.to_bits_le()?;
+                let g_r_computed = g.scalar_mul_le(vote_r_i_bits.iter())?;
+                g_r[i].enforce_equal(&g_r_computed)?;
+
+                let vote_cm_computed = g.scalar_mul_le(vote_m_i.to_bits_le()?.iter())? + h.scalar_mul_le(vote_r_i_bits.iter())?;
+                vote_cm[i].enforce_equal(&vote_cm_computed)?;
+            }
+
+
+            // 5, 6. vote_m is a valid ballot under ballot_mode
+            ballot_mode.enforce_valid(vote_m, num_of_candidates, ballot_bound.as_ref().or(weight.as_ref()))?;
+
+
+            // 8. vote_m[i] == 0 for i >= num_of_candidates (num_of_candidates <= candidate_limit)
+            let mut is_unused = Boolean::Constant(false);
+            for (i, vote_m_i) in vote_m.iter().enumerate() {
+                let i = FpVar::<C::BaseField>::constant(C::BaseField::from(i as u64));
+                is_unused = is_unused.or(&num_of_candidates.is_eq(&i)?)?;
+                vote_m_i.conditional_enforce_equal(&zero, &is_unused)?;
+            }
+            let candidate_limit = FpVar::<C::BaseField>::constant(C::BaseField::from(vote_m.len() as u64));
+            is_unused = is_unused.or(&num_of_candidates.is_eq(&candidate_limit)?)?;
+            is_unused.enforce_equal(&Boolean::Constant(true))?;
+        }
         Ok(())
     }
 }
@@ -181,136 +439,249 @@ where
 
     fn generate_circuit(
             g: C::Affine,
-            ck: Vec<C::Affine>,
+            h: C::Affine,  // tally public key h = g^x
             sk: C::BaseField,
             pk: C::Affine,
-            tree_height: u64,
-            voting_round: u64,
-            num_of_candidates: u64,
-            num_of_voters: u64,
-            vote_index: u64,  // index of the candidate that the voter is voting for
-            voter_pos: u64,  // index of the voter
-            candidate_limit: u64,
+            contests: Vec<Contest>,
+            ballots: Vec<Ballot>,  // choice of the voter in every contest
+            config: ElectionConfig<C::BaseField>,
         ) -> Result<Self::Output, crate::Error> {
         use ark_ec::AffineRepr;
-        use ark_std::{UniformRand, One};
-        use ark_std::rand::RngCore;
-        use ark_std::rand::SeedableRng;
-        use ark_std::test_rng;
+        use ark_std::UniformRand;
         use std::str::FromStr;
         use num_bigint::BigUint;
         use crate::circuits::voting::parser::*;
 
-        // let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(test_rng().next_u64());
+        let ElectionConfig { tree_height, contract_addr, voting_round, num_of_voters, voter_pos, version, revoked_voters, relayer, semaphore, quad_tree } = config;
         let mut rng = thread_rng();
-        println!("ck: {:?}", ck.iter().map(|x| x.to_string()).collect::<Vec<String>>());
+        println!("h: {:?}", h.to_string());
 
         // Generate the hash parameters
         let hash_params: PoseidonConfig<<<C as CurveGroup>::Affine as AffineRepr>::BaseField> = get_poseidon_params();
 
         // addr = CRH(pk)
         let (pk_x, pk_y) = pk.xy().unwrap();
-        let addr = Self::H::evaluate(&hash_params, vec![pk_x.clone(), pk_y.clone()]).unwrap();
+        let addr = Self::H::evaluate(&hash_params, vec![*pk_x, *pk_y]).unwrap();
+
+        // Semaphore identity. Mock voters derive theirs from sk.
+        let poseidon = CircomPoseidon::<Self::F>::new();
+        let mock_identity = |sk: Self::F| SemaphoreIdentity { trapdoor: sk, nullifier: Self::H::evaluate(&hash_params, vec![sk]).unwrap() };
+        let identity = semaphore.then(|| mock_identity(sk));
+        let secret = identity.map_or(sk, |identity| identity.nullifier);
 
         // voting round
         let voting_round = Self::F::from(voting_round);
+        let election_id = Self::H::evaluate(&hash_params, vec![nullifier_domain(), contract_addr, voting_round]).unwrap();
 
-        // sn
-        let sn = Self::H::evaluate(&hash_params, vec![sk, voting_round]).unwrap();
+        // sn, prev_sn
+        let version = version.map(Self::F::from);
+        let sn = match version {
+            Some(version) => Self::H::evaluate(&hash_params, vec![secret, election_id, version]).unwrap(),
+            None => Self::H::evaluate(&hash_params, vec![secret, election_id]).unwrap(),
+        };
+        let prev_sn = match version {
+            Some(version) if !version.is_zero() => Some(Self::H::evaluate(&hash_params, vec![secret, election_id, version - Self::F::ONE]).unwrap()),
+            Some(_) => Some(Self::F::zero()),
+            None => None,
+        };
         println!("sn: {:?}", sn.to_string());
 
-        // vote_m
-        let mut vote_m = vec![Self::F::zero(); candidate_limit as usize];
-        if (vote_index as usize) < candidate_limit as usize {
-            vote_m[vote_index as usize] = Self::F::one();
+        if contests.len() != ballots.len() {
+            return Err("every contest needs exactly one ballot".into());
         }
 
-        let vote_m_str = vote_m.iter().map(|x| x.to_string()).collect::<Vec<String>>();
-        println!("vote_m: {:?}", vote_m_str);
-
-        // vote_r
-        let mut vote_r = vec![];
-        for i in 0..candidate_limit {
-            if i < num_of_candidates {
-                let random = Self::F::rand(&mut rng);
-                vote_r.push(random);
-            } else {
-                vote_r.push(Self::F::zero());
+        // weights of the voters, stored next to addr in a weighted voter tree
+        let weights = if contests.iter().any(|contest| contest.ballot_mode.is_weighted()) {
+            let mut weights = vec![];
+            for i in 0..num_of_voters as usize {
+                weights.push(parse_user(&get_user(i)?)?.weight);
             }
+            Some(weights)
+        } else {
+            None
+        };
+        let weight = match weights.as_ref() {
+            Some(weights) => Some(*weights.get(voter_pos as usize).ok_or("voter_pos is out of range for num_of_voters")?),
+            None => None,
+        };
+        if semaphore && weights.is_some() {
+            return Err("a Semaphore group has no weights".into());
         }
-
-        // g^r
-        let mut g_r = vec![];
-        for i in 0..candidate_limit as usize {
-            let g_r_i = g.mul_bigint(&vote_r[i].into_bigint());
-            g_r.push(g_r_i.into_affine());
+        if semaphore && quad_tree {
+            return Err("a Semaphore group is a binary tree".into());
         }
 
-        let g_r_str = g_r.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        let mut contest_instances = vec![];
+        let mut contest_witnesses = vec![];
+        for (contest, ballot) in contests.iter().zip(ballots.iter()) {
+            let Contest { ballot_mode, ballot_bound, num_of_candidates, candidate_limit } = *contest;
+            if ballot_mode.is_weighted() && ballot_bound.is_some() {
+                return Err("weighted ballots take the weight from the voter tree, not ballot_bound".into());
+            }
 
-        // vote_cm (g^mh^r)
-        let mut vote_cm = vec![];
-        for i in 0..candidate_limit as usize {
-            let vote_cm_i = ck[0].mul_bigint(&vote_m[i].into_bigint()) + ck[1].mul_bigint(&vote_r[i].into_bigint());
-            vote_cm.push(vote_cm_i.into_affine());
-        }
+            // vote_m
+            let vote_m: Vec<Self::F> = ballot.to_vote_m(ballot_mode, ballot_bound.or(weight.filter(|_| ballot_mode.is_weighted())), num_of_candidates, candidate_limit)?;
+
+            let vote_m_str = vote_m.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+            println!("vote_m: {:?}", vote_m_str);
+
+            // vote_r
+            let mut vote_r = vec![];
+            for i in 0..candidate_limit {
+                if i < num_of_candidates {
+                    let random = Self::F::rand(&mut rng);
+                    vote_r.push(random);
+                } else {
+                    vote_r.push(Self::F::zero());
+                }
+            }
 
-        let vote_cm_str = vote_cm.iter().map(|x| x.to_string()).collect::<Vec<String>>();
-        
-        // print g^r_i, vote_cm_i
-        for i in 0..candidate_limit as usize {
-            println!("g^r_{}: {:?}", i, g_r_str[i]);
-            println!("g^mh^r_{}: {:?}\n", i, vote_cm_str[i]);
-        }
+            // g^r
+            let mut g_r = vec![];
+            for vote_r_i in vote_r.iter() {
+                let g_r_i = g.mul_bigint(vote_r_i.into_bigint());
+                g_r.push(g_r_i.into_affine());
+            }
 
+            let g_r_str = g_r.iter().map(|x| x.to_string()).collect::<Vec<String>>();
 
-        // Merkle tree
-        let leaf_crh_params = hash_params.clone();
-        let two_to_one_params = hash_params.clone();
+            // vote_cm (g^mh^r)
+            let mut vote_cm = vec![];
+            for i in 0..candidate_limit as usize {
+                let vote_cm_i = g.mul_bigint(vote_m[i].into_bigint()) + h.mul_bigint(vote_r[i].into_bigint());
+                vote_cm.push(vote_cm_i.into_affine());
+            }
 
-        let num_leaves = 2_usize.pow(tree_height as u32);
-        let mut leaves = vec![];
+            let vote_cm_str = vote_cm.iter().map(|x| x.to_string()).collect::<Vec<String>>();
 
-        for i in 0..num_of_voters as usize {
-            let user_addr_str: String = get_user(i).unwrap().addr;
-            let user_addr_bigint = BigUint::from_str(&user_addr_str).unwrap();
-            let user_addr_bytes = user_addr_bigint.to_bytes_le();
-            let user_addr = Self::F::from_le_bytes_mod_order(&user_addr_bytes);
-            leaves.push([user_addr]);
+            // print g^r_i, vote_cm_i
+            for i in 0..candidate_limit as usize {
+                println!("g^r_{}: {:?}", i, g_r_str[i]);
+                println!("g^mh^r_{}: {:?}\n", i, vote_cm_str[i]);
+            }
+
+            contest_instances.push(ContestInstance {
+                num_of_candidates: Some(Self::F::from(num_of_candidates)),
+                ballot_bound: ballot_bound.map(Self::F::from),
+                g_r: Some(g_r),
+                vote_cm: Some(vote_cm),
+            });
+
+            let choice = if ballot_mode.is_indexed() {
+                Some(ballot.to_choice(ballot_mode, num_of_candidates)?)
+            } else {
+                None
+            };
+
+            contest_witnesses.push(ContestWitness {
+                choice,
+                vote_m: choice.is_none().then_some(vote_m),
+                vote_r: Some(vote_r),
+            });
         }
-    
-        while leaves.len() < num_leaves {
-            leaves.push([Self::F::zero()]);
+
+
+        // Merkle tree over the commitments registered for this election.
+        // Mock voters derive their salt from sk; any secret salt works.
+        let salt = Self::H::evaluate(&hash_params, vec![salt_domain(), sk, election_id]).unwrap();
+        let mut commitments = vec![];
+        let mut addrs = vec![];
+
+        let to_field = |value: &str| Self::F::from_le_bytes_mod_order(&BigUint::from_str(value).unwrap().to_bytes_le());
+        for i in 0..num_of_voters as usize {
+            let user = get_user(i)?;
+            let user_addr = to_field(&user.addr);
+            addrs.push(user_addr);
+
+            let user_salt = Self::H::evaluate(&hash_params, vec![salt_domain(), to_field(&user.sk), election_id]).unwrap();
+            commitments.push(identity_commitment(&hash_params, to_field(&user.pk[0]), to_field(&user.pk[1]), user_salt)?);
         }
 
-        let tree = MerkleTree::<MerkleTreeParams<Self::F>>::new(
-            &leaf_crh_params,
-            &two_to_one_params,
-            leaves,
-        )?;
-        
-        let root = tree.root().clone();
+        let (root, merkle_proof, semaphore_proof, quad_tree_proof) = if semaphore {
+            // the identity commitments stand in for addr, also in the revocation list
+            let mut group = SemaphoreGroup::new(tree_height as usize, Self::F::zero())?;
+            addrs.clear();
+            for i in 0..num_of_voters as usize {
+                let commitment = mock_identity(to_field(&get_user(i)?.sk)).commitment(&poseidon)?;
+                group.add_member(commitment)?;
+                addrs.push(commitment);
+            }
+            (group.root()?, None, Some(group.generate_proof(voter_pos as usize)?), None)
+        } else if quad_tree {
+            let leaves = voter_leaves(&commitments, weights.as_deref(), tree_height)?;
+            let tree = QuadMerkleTree::new(&get_quad_poseidon_params(), &leaves)?;
+            (tree.root(), None, None, Some(tree.generate_proof(voter_pos as usize)?))
+        } else {
+            // only the registered leaves are hashed, the free slots are precomputed
+            let empty_leaf = empty_voter_leaf(weights.is_some());
+            let mut tree = IncrementalMerkleTree::new(hash_params.clone(), tree_height as usize, empty_leaf, 1)?;
+            for leaf in registered_voter_leaves(&commitments, weights.as_deref())?.iter() {
+                tree.append(leaf)?;
+            }
+            (tree.root(), Some(tree.generate_proof(voter_pos as usize)?), None, None)
+        };
         println!("Root: {:?}", root.to_string());
-        let merkle_proof = tree.generate_proof(voter_pos as usize)?;
+        let addr = match identity {
+            Some(identity) => identity.commitment(&poseidon)?,
+            None => addr,
+        };
+
+        // revocation tree
+        let mut revocation_root = None;
+        let mut non_membership_proof = None;
+        if let Some(revoked_voters) = revoked_voters {
+            let mut revocation_tree = IndexedMerkleTree::new(hash_params.clone(), tree_height as usize)?;
+            for i in revoked_voters.iter() {
+                let revoked_addr = addrs.get(*i as usize).ok_or("revoked voter is not registered")?;
+                revocation_tree.insert(*revoked_addr)?;
+            }
+            revocation_root = Some(revocation_tree.root());
+            non_membership_proof = Some(revocation_tree.non_membership_proof(&addr)?);
+        }
 
         let instance = VotingInstance {
             voting_round: Some(voting_round),
+            sn: Some(sn),
+            contract_addr: Some(contract_addr),
+            submitter: relayer.map(|(submitter, _)| submitter),
+            fee: relayer.map(|(_, fee)| fee),
+            prev_sn,
             root: Some(root),
-            vote_cm: Some(vote_cm),
+            revocation_root,
+            h: Some(h),
+            contests: contest_instances,
         };
 
         let witness = VotingWitness {
-            sk: Some(sk),
-            pk: Some(pk),
-            addr: Some(addr),
-            vote_m: Some(vote_m),
-            vote_r: Some(vote_r),
-            sn: Some(sn),
+            sk: (!semaphore).then_some(sk),
+            version,
+            pk: (!semaphore).then_some(pk),
+            addr: (!semaphore).then_some(addr),
+            salt: (!semaphore).then_some(salt),
+            identity_trapdoor: identity.map(|identity| identity.trapdoor),
+            identity_nullifier: identity.map(|identity| identity.nullifier),
+            weight: weight.map(Self::F::from),
+            contests: contest_witnesses,
             leaf_pos: Some(voter_pos as u32),
-            tree_proof: Some(merkle_proof),
+            tree_proof: merkle_proof,
+            semaphore_proof,
+            quad_tree_proof,
+            low_leaf: non_membership_proof.as_ref().map(|proof| proof.low_leaf.to_vec()),
+            low_leaf_pos: non_membership_proof.as_ref().map(|proof| proof.low_leaf_pos as u32),
+            revocation_proof: non_membership_proof.map(|proof| proof.path),
+        };
+
+        let ballot_modes = contests.iter().map(|contest| contest.ballot_mode).collect();
+
+        let options = VotingOptions {
+            revoting: version.is_some(),
+            revocation: revocation_root.is_some(),
+            relayer: relayer.is_some(),
+            semaphore,
+            quad_tree,
         };
 
-        Ok(Self::new(g, ck, hash_params, instance, witness))
+        Ok(Self::new(g, hash_params, ballot_modes, options, instance, witness))
     }
 }
 
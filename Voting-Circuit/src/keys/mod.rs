@@ -1,2 +1,4 @@
 pub mod voting;
-pub mod tally;
\ No newline at end of file
+pub mod tally;
+pub mod maci;
+pub mod registration;
\ No newline at end of file
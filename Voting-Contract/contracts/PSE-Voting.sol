@@ -10,6 +10,14 @@ contract PseVoting {
     
     uint candidateLimit;
     uint256[] vk;
+    // whether vk is for proofs bound to a relayer, with the submitter and fee as public inputs
+    bool relayed;
+    // whether vk is for re-voting proofs, with prev_sn as a public input
+    bool revoting;
+    // whether vk is for proofs against a revocation list, with revocation_root as a public input
+    bool revocation;
+    // the ballot_bound of the one contest of the voting circuit, zero for a ballot mode without one
+    uint ballotBound;
 
     struct Candidate {
         uint candidateNumber;
@@ -30,25 +38,47 @@ contract PseVoting {
         uint currentVotingRound;
         mapping(address => Candidate) candidates;
         mapping(uint => bool) serialNumberUsed;
+        mapping(uint => bool) registrationNullifierUsed;
         address[] candidateAddresses;
         uint totalCandidate;
         uint[] voterAddresses;
         uint[] proofs;
         uint root;
+        // root of the indexed tree of revoked voters, set by the owner and updated while voting
+        uint revocationRoot;
+        // only when re-voting: the ballots no later one has replaced, and where each one's votes
+        // sit in the voteCount of every candidate
+        mapping(uint => bool) liveBallot;
+        mapping(uint => uint[]) voteIndices;
     }
 
+    // positions of the public inputs that come before any optional one, see inputsLength
+    uint constant VOTING_ROUND_INPUT = 0;
+    uint constant SN_INPUT = 1;
+    uint constant CONTRACT_INPUT = 2;
+    // only in a relayed layout, right after contract_addr
+    uint constant SUBMITTER_INPUT = 3;
+    uint constant FEE_INPUT = 4;
+
     uint public votingRoundCounter;
     mapping(uint => VotingRound) public votingRounds;
 
     event VotingRoundCreated(uint votingRoundNumber);
     event CandidateRegistered(uint indexed votingRoundNumber, address indexed candidateAddress, string name);
     event VoteSubmitted(uint indexed votingRoundNumber, uint serialNumber, Vote[] votes);
+    event RevocationRootUpdated(uint indexed votingRoundNumber, uint revocationRoot);
+    event BallotReplaced(uint indexed votingRoundNumber, uint previousSerialNumber, uint serialNumber);
+    event RelayedVoteSubmitted(uint indexed votingRoundNumber, uint serialNumber, address indexed submitter, uint fee);
 
-    constructor(uint[] memory _ck, uint[] memory _vk, uint _candidateLimit) {
+    constructor(uint[] memory _ck, uint[] memory _vk, uint _candidateLimit, bool _relayed, bool _revoting, bool _revocation, uint _ballotBound) {
         owner = msg.sender;
         votingRoundCounter = 0;
         vk = _vk;
         candidateLimit = _candidateLimit;
+        relayed = _relayed;
+        revoting = _revoting;
+        revocation = _revocation;
+        ballotBound = _ballotBound;
         Bn128.G1Point memory g = Bn128.G1Point(_ck[0], _ck[1]);
         Bn128.G1Point memory h = Bn128.G1Point(_ck[2], _ck[3]);
         ck.push(g);
@@ -60,9 +90,58 @@ contract PseVoting {
         _;
     }
 
+    // Public inputs of the voting circuit, laid out as VotingInstance::public_inputs:
+    // voting_round, sn, contract_addr, [submitter, fee], [prev_sn], root, [revocation_root], h.x, h.y,
+    // then for the one contest num_of_candidates, [ballot_bound], and the x and y of candidateLimit
+    // g_r and candidateLimit vote_cm points.
+    function inputsLength() public view returns (uint) {
+        uint length = 7 + 4 * candidateLimit;
+        if (relayed) length += 2;
+        if (revoting) length += 1;
+        if (revocation) length += 1;
+        if (ballotBound != 0) length += 1;
+        return length;
+    }
+
+    // root follows the submitter and fee of a relayed layout and the prev_sn of a re-voting one
+    function rootInput() internal view returns (uint) {
+        uint index = 3;
+        if (relayed) index += 2;
+        if (revoting) index += 1;
+        return index;
+    }
+
+    // only in a re-voting layout, right before the root
+    function prevSnInput() internal view returns (uint) {
+        return rootInput() - 1;
+    }
+
+    // h follows the root and the revocation_root of a layout with a revocation list
+    function hInput() internal view returns (uint) {
+        return revocation ? rootInput() + 2 : rootInput() + 1;
+    }
+
+    // num_of_candidates of the contest, followed by its ballot_bound if it has one
+    function contestInput() internal view returns (uint) {
+        return hInput() + 2;
+    }
+
+    // the candidateLimit g_r points of the contest, its vote_cm points come right after them
+    function gRInput() internal view returns (uint) {
+        return ballotBound != 0 ? contestInput() + 2 : contestInput() + 1;
+    }
+
     function proofVerify(uint[] memory _proof, uint[] memory _inputs) internal view returns (bool) {
         require(_proof.length == 10, "proof length must be 10");
-        require(_inputs.length == 4 + candidateLimit, "Invalid inputs length");
+        require(_inputs.length == inputsLength(), "Invalid inputs length");
+        return true;
+    }
+
+    // registration proof: knowledge of sk with pk = g^sk and _commitment = Poseidon(pk.x, pk.y, salt).
+    // Its inputs are voting_round, nullifier, contract_addr and _commitment, with one nullifier per sk and election.
+    function registrationProofVerify(uint[] memory _proof, uint[] memory _inputs) internal pure returns (bool) {
+        require(_proof.length == 10, "proof length must be 10");
+        require(_inputs.length == 4, "Invalid inputs length");
         return true;
     }
 
@@ -104,6 +183,16 @@ contract PseVoting {
         round.votingOpen = true;
     }
 
+    // Revocations after openVoting only take effect through a new root; proofs against the
+    // previous one are rejected from then on.
+    function setRevocationRoot(uint _votingRoundNumber, uint _revocationRoot) external onlyOwner {
+        VotingRound storage round = votingRounds[_votingRoundNumber];
+        require(revocation, "Voters are not revoked in this election");
+        require(round.registrationOpen || round.votingOpen, "Registration and voting are closed");
+        round.revocationRoot = _revocationRoot;
+        emit RevocationRootUpdated(_votingRoundNumber, _revocationRoot);
+    }
+
     function CloseVoting(uint votingRoundNumber) external onlyOwner {
         VotingRound storage round = votingRounds[votingRoundNumber];
         require(round.votingOpen, "Voting is not open");
@@ -132,27 +221,99 @@ contract PseVoting {
         emit CandidateRegistered(_votingRoundNumber, msg.sender, _name);
     }
 
-    function registerVoter(uint _votingRoundNumber, uint _addr) external {
+    // _commitment = Poseidon(pk.x, pk.y, salt) with a fresh salt for every voting round
+    function registerVoter(uint _votingRoundNumber, uint _commitment, uint[] memory proof, uint[] memory inputs) external {
         VotingRound storage round = votingRounds[_votingRoundNumber];
         require(round.registrationOpen, "Registration is not open");
+        require(registrationProofVerify(proof, inputs), "Invalid registration proof");
+        require(inputs[0] == _votingRoundNumber, "Voting round does not match the proof");
+        require(inputs[2] == uint(uint160(address(this))), "Proof is for another election");
+        require(inputs[3] == _commitment, "Commitment does not match the proof");
+        require(!round.registrationNullifierUsed[inputs[1]], "Key already registered");
 
         for (uint i = 0; i < round.voterAddresses.length; i++) {
-            require(round.voterAddresses[i] != _addr, "Voter already registered");
+            require(round.voterAddresses[i] != _commitment, "Voter already registered");
         }
 
-        round.voterAddresses.push(_addr);
+        round.registrationNullifierUsed[inputs[1]] = true;
+        round.voterAddresses.push(_commitment);
     }
 
     function submitVote(uint _votingRoundNumber, uint sn, Vote[] memory voteList, uint[] memory proof, uint[] memory inputs) external {
         VotingRound storage round = votingRounds[_votingRoundNumber];
+        require(!relayed, "Votes must be submitted through submitRelayedVote");
+        _checkInputs(round, _votingRoundNumber, sn, voteList, proof, inputs);
+        _recordVote(round, _votingRoundNumber, sn, revoting ? inputs[prevSnInput()] : 0, voteList);
+    }
+
+    // Only for a relayed election, whose proofs are bound to a relayer: the submitter, who must be
+    // msg.sender, and the fee it may claim.
+    function submitRelayedVote(uint _votingRoundNumber, uint sn, Vote[] memory voteList, uint[] memory proof, uint[] memory inputs) external {
+        VotingRound storage round = votingRounds[_votingRoundNumber];
+        require(relayed, "Votes are not relayed in this election");
+        _checkInputs(round, _votingRoundNumber, sn, voteList, proof, inputs);
+        require(inputs[SUBMITTER_INPUT] == uint(uint160(msg.sender)), "Proof is bound to another submitter");
+        _recordVote(round, _votingRoundNumber, sn, revoting ? inputs[prevSnInput()] : 0, voteList);
+
+        emit RelayedVoteSubmitted(_votingRoundNumber, sn, msg.sender, inputs[FEE_INPUT]);
+    }
+
+    function _checkInputs(VotingRound storage round, uint _votingRoundNumber, uint sn, Vote[] memory voteList, uint[] memory proof, uint[] memory inputs) internal view {
         require(proofVerify(proof, inputs), "Invalid proof");
+        require(inputs[VOTING_ROUND_INPUT] == _votingRoundNumber, "Voting round does not match the proof");
+        require(inputs[SN_INPUT] == sn, "Serial number does not match the proof");
+        require(inputs[CONTRACT_INPUT] == uint(uint160(address(this))), "Proof is for another election");
+        require(inputs[rootInput()] == round.root, "Proof is for another voter tree");
+        if (revocation) {
+            require(inputs[rootInput() + 1] == round.revocationRoot, "Proof is against another revocation list");
+        }
+        require(inputs[hInput()] == ck[1].X && inputs[hInput() + 1] == ck[1].Y, "Proof is for another tally key");
+        require(inputs[contestInput()] == round.totalCandidate, "Proof is for another candidate list");
+        if (ballotBound != 0) {
+            require(inputs[contestInput() + 1] == ballotBound, "Proof is under another ballot bound");
+        }
+        require(voteList.length == round.totalCandidate, "One vote per candidate");
+        _checkVotes(voteList, inputs);
+    }
+
+    // the proof is only for the g_r and vote_cm = g^m * h^r it takes as inputs
+    function _checkVotes(Vote[] memory voteList, uint[] memory inputs) internal view {
+        uint gR = gRInput();
+        uint voteCm = gR + 2 * candidateLimit;
+        for (uint i = 0; i < voteList.length; i++) {
+            require(
+                voteList[i].g_r.X == inputs[gR + 2 * i] && voteList[i].g_r.Y == inputs[gR + 2 * i + 1] &&
+                voteList[i].g_mh_r.X == inputs[voteCm + 2 * i] && voteList[i].g_mh_r.Y == inputs[voteCm + 2 * i + 1],
+                "Votes do not match the proof"
+            );
+        }
+    }
+
+    // A zero prevSn casts a new ballot. Otherwise the votes take the place of those of the live
+    // ballot prevSn, so a voter who re-votes is still counted once.
+    function _recordVote(VotingRound storage round, uint _votingRoundNumber, uint sn, uint prevSn, Vote[] memory voteList) internal {
         require(!round.serialNumberUsed[sn], "Serial number already used");
         round.serialNumberUsed[sn] = true;
 
-        for (uint i = 0; i < voteList.length; i++) {
-            address candidateAddress = round.candidateAddresses[i];
-            round.candidates[candidateAddress].voteCount.push(voteList[i]);
+        if (prevSn == 0) {
+            for (uint i = 0; i < voteList.length; i++) {
+                Vote[] storage voteCount = round.candidates[round.candidateAddresses[i]].voteCount;
+                if (revoting) round.voteIndices[sn].push(voteCount.length);
+                voteCount.push(voteList[i]);
+            }
+        } else {
+            require(round.liveBallot[prevSn], "Previous ballot is unknown or already replaced");
+            uint[] storage indices = round.voteIndices[prevSn];
+            require(indices.length == voteList.length, "Ballot must cover the same candidates");
+            for (uint i = 0; i < voteList.length; i++) {
+                round.candidates[round.candidateAddresses[i]].voteCount[indices[i]] = voteList[i];
+            }
+            round.voteIndices[sn] = indices;
+            delete round.voteIndices[prevSn];
+            round.liveBallot[prevSn] = false;
+            emit BallotReplaced(_votingRoundNumber, prevSn, sn);
         }
+        if (revoting) round.liveBallot[sn] = true;
 
         emit VoteSubmitted(_votingRoundNumber, sn, voteList);
     }
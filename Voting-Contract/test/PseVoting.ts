@@ -0,0 +1,308 @@
+import { loadFixture } from "@nomicfoundation/hardhat-toolbox/network-helpers";
+import { expect } from "chai";
+import { ethers } from "hardhat";
+
+// public inputs of real voting circuits, written by test_voting_contract_inputs in Voting-Circuit
+import fixture from "./fixtures/voting_inputs.json";
+// and of registration circuits, by test_registration_contract_inputs
+import registrations from "./fixtures/registration_inputs.json";
+
+// g and the tally key h of the circuits
+const ck = fixture.plain.ck;
+const candidateLimit = 2;
+const proof = new Array(10).fill(0);
+
+describe("PseVoting", function () {
+  // the contract addresses are bound into the proofs, so the deployments have to come first
+  async function deployFixture() {
+    const [owner, submitter, other] = await ethers.getSigners();
+    const plain = await ethers.deployContract("PseVoting", [ck, [], candidateLimit, false, false, false, 0]);
+    // relayed, re-voting and with a revocation list: every optional input sits before the root
+    const relayed = await ethers.deployContract("PseVoting", [ck, [], candidateLimit, true, true, true, 0]);
+    // approval of at most one candidate
+    const bounded = await ethers.deployContract("PseVoting", [ck, [], candidateLimit, false, false, false, 1]);
+
+    expect(await plain.getAddress()).to.equal(ethers.getAddress(fixture.plain.contract));
+    expect(await relayed.getAddress()).to.equal(ethers.getAddress(fixture.relayed.contract));
+    expect(await bounded.getAddress()).to.equal(ethers.getAddress(fixture.bounded.contract));
+    expect(submitter.address).to.equal(ethers.getAddress(fixture.relayed.submitter));
+    return { owner, submitter, other, plain, relayed, bounded };
+  }
+
+  async function openVoting(contract: any, round: { votingRound: string; root: string; revocationRoot?: string }) {
+    await contract.createVotingRound();
+    await contract.openRegistration(round.votingRound);
+    // the candidates the votes are for
+    const signers = await ethers.getSigners();
+    for (const [i, candidate] of signers.slice(3, 3 + candidateLimit).entries()) {
+      await contract.connect(candidate).registerCandidate(round.votingRound, `candidate ${i}`);
+    }
+    await contract.openVoting(round.votingRound, round.root);
+    if (round.revocationRoot !== undefined) {
+      await contract.setRevocationRoot(round.votingRound, round.revocationRoot);
+    }
+  }
+
+  describe("registerVoter", function () {
+    it("takes the inputs of a real proof", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const [first, , other] = registrations;
+      await plain.createVotingRound();
+      await plain.openRegistration(first.votingRound);
+
+      await plain.registerVoter(first.votingRound, first.cm, proof, first.inputs);
+      await plain.registerVoter(other.votingRound, other.cm, proof, other.inputs);
+      expect(await plain.getVoterAddresses(first.votingRound)).to.deep.equal([BigInt(first.cm), BigInt(other.cm)]);
+    });
+
+    it("rejects a second commitment of the same key", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const [first, second] = registrations;
+      await plain.createVotingRound();
+      await plain.openRegistration(first.votingRound);
+
+      await plain.registerVoter(first.votingRound, first.cm, proof, first.inputs);
+      await expect(plain.registerVoter(second.votingRound, second.cm, proof, second.inputs))
+        .to.be.revertedWith("Key already registered");
+    });
+
+    it("rejects a proof for another election", async function () {
+      const { relayed } = await loadFixture(deployFixture);
+      const [first] = registrations;
+      await relayed.createVotingRound();
+      await relayed.openRegistration(first.votingRound);
+
+      await expect(relayed.registerVoter(first.votingRound, first.cm, proof, first.inputs))
+        .to.be.revertedWith("Proof is for another election");
+    });
+  });
+
+  describe("submitVote", function () {
+    it("takes the inputs of a real proof", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, round);
+
+      expect(await plain.inputsLength()).to.equal(round.inputs.length);
+      await expect(plain.submitVote(round.votingRound, round.sn, round.votes, proof, round.inputs))
+        .to.emit(plain, "VoteSubmitted");
+    });
+
+    it("takes the votes of a real proof", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, round);
+
+      await expect(plain.submitVote(round.votingRound, round.sn, round.votes, proof, round.inputs))
+        .to.emit(plain, "VoteSubmitted");
+    });
+
+    it("rejects votes the proof is not for", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, round);
+
+      const votes = [...round.votes].reverse();
+      await expect(plain.submitVote(round.votingRound, round.sn, votes, proof, round.inputs))
+        .to.be.revertedWith("Votes do not match the proof");
+    });
+
+    it("rejects a proof for another tally key", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, round);
+
+      const inputs = [...round.inputs];
+      inputs[4] = "1";
+      await expect(plain.submitVote(round.votingRound, round.sn, [], proof, inputs))
+        .to.be.revertedWith("Proof is for another tally key");
+    });
+
+    it("rejects a proof for another candidate list", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, round);
+
+      const inputs = [...round.inputs];
+      inputs[6] = "1";
+      await expect(plain.submitVote(round.votingRound, round.sn, round.votes, proof, inputs))
+        .to.be.revertedWith("Proof is for another candidate list");
+    });
+
+    it("rejects a ballot without a vote for every candidate", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, round);
+
+      await expect(plain.submitVote(round.votingRound, round.sn, round.votes.slice(1), proof, round.inputs))
+        .to.be.revertedWith("One vote per candidate");
+    });
+
+    it("takes a proof under the ballot bound of the election", async function () {
+      const { bounded } = await loadFixture(deployFixture);
+      const round = fixture.bounded;
+      await openVoting(bounded, round);
+
+      expect(await bounded.inputsLength()).to.equal(round.inputs.length);
+      await expect(bounded.submitVote(round.votingRound, round.sn, round.votes, proof, round.inputs))
+        .to.emit(bounded, "VoteSubmitted");
+    });
+
+    it("rejects a proof under another ballot bound", async function () {
+      const { bounded } = await loadFixture(deployFixture);
+      const round = fixture.bounded;
+      await openVoting(bounded, round);
+
+      // approval of both candidates
+      const inputs = [...round.inputs];
+      inputs[7] = "2";
+      await expect(bounded.submitVote(round.votingRound, round.sn, round.votes, proof, inputs))
+        .to.be.revertedWith("Proof is under another ballot bound");
+    });
+
+    it("rejects a reused serial number", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, round);
+
+      await plain.submitVote(round.votingRound, round.sn, round.votes, proof, round.inputs);
+      await expect(plain.submitVote(round.votingRound, round.sn, round.votes, proof, round.inputs))
+        .to.be.revertedWith("Serial number already used");
+    });
+
+    it("rejects inputs of another layout", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, round);
+
+      await expect(plain.submitVote(round.votingRound, round.sn, [], proof, round.inputs.slice(1)))
+        .to.be.revertedWith("Invalid inputs length");
+    });
+
+    it("rejects a proof for another election", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, round);
+
+      const inputs = [...round.inputs];
+      inputs[2] = "1";
+      await expect(plain.submitVote(round.votingRound, round.sn, [], proof, inputs))
+        .to.be.revertedWith("Proof is for another election");
+    });
+
+    it("rejects a proof for another voter tree", async function () {
+      const { plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, { ...round, root: "1" });
+
+      await expect(plain.submitVote(round.votingRound, round.sn, [], proof, round.inputs))
+        .to.be.revertedWith("Proof is for another voter tree");
+    });
+
+    it("rejects a relayed proof", async function () {
+      const { relayed } = await loadFixture(deployFixture);
+      const round = fixture.relayed;
+      await openVoting(relayed, round);
+
+      await expect(relayed.submitVote(round.votingRound, round.sn, [], proof, round.inputs))
+        .to.be.revertedWith("Votes must be submitted through submitRelayedVote");
+    });
+  });
+
+  describe("submitRelayedVote", function () {
+    it("takes the inputs of a real proof from the submitter", async function () {
+      const { submitter, relayed } = await loadFixture(deployFixture);
+      const round = fixture.relayedFirst;
+      await openVoting(relayed, round);
+
+      expect(await relayed.inputsLength()).to.equal(round.inputs.length);
+      await expect(relayed.connect(submitter).submitRelayedVote(round.votingRound, round.sn, round.votes, proof, round.inputs))
+        .to.emit(relayed, "RelayedVoteSubmitted")
+        .withArgs(round.votingRound, round.sn, submitter.address, round.fee);
+    });
+
+    it("replaces the ballot a re-vote names", async function () {
+      const { submitter, relayed } = await loadFixture(deployFixture);
+      const [first, round] = [fixture.relayedFirst, fixture.relayed];
+      await openVoting(relayed, round);
+
+      await relayed.connect(submitter).submitRelayedVote(first.votingRound, first.sn, first.votes, proof, first.inputs);
+      await expect(relayed.connect(submitter).submitRelayedVote(round.votingRound, round.sn, round.votes, proof, round.inputs))
+        .to.emit(relayed, "BallotReplaced")
+        .withArgs(round.votingRound, first.sn, round.sn);
+    });
+
+    it("rejects a re-vote of an unknown ballot", async function () {
+      const { submitter, relayed } = await loadFixture(deployFixture);
+      const round = fixture.relayed;
+      await openVoting(relayed, round);
+
+      await expect(relayed.connect(submitter).submitRelayedVote(round.votingRound, round.sn, round.votes, proof, round.inputs))
+        .to.be.revertedWith("Previous ballot is unknown or already replaced");
+    });
+
+    it("rejects a second re-vote of the same ballot", async function () {
+      const { submitter, relayed } = await loadFixture(deployFixture);
+      const [first, round] = [fixture.relayedFirst, fixture.relayed];
+      await openVoting(relayed, round);
+
+      await relayed.connect(submitter).submitRelayedVote(first.votingRound, first.sn, first.votes, proof, first.inputs);
+      await relayed.connect(submitter).submitRelayedVote(round.votingRound, round.sn, round.votes, proof, round.inputs);
+      // a fork of the chain from the replaced ballot, which would count the voter twice
+      const inputs = [...round.inputs];
+      inputs[1] = "1";
+      await expect(relayed.connect(submitter).submitRelayedVote(round.votingRound, "1", round.votes, proof, inputs))
+        .to.be.revertedWith("Previous ballot is unknown or already replaced");
+    });
+
+    it("rejects a proof against a stale revocation list", async function () {
+      const { submitter, relayed } = await loadFixture(deployFixture);
+      const round = fixture.relayed;
+      await openVoting(relayed, round);
+
+      // the registrar revokes another voter after the proof was made
+      await relayed.setRevocationRoot(round.votingRound, "1");
+      await expect(relayed.connect(submitter).submitRelayedVote(round.votingRound, round.sn, [], proof, round.inputs))
+        .to.be.revertedWith("Proof is against another revocation list");
+    });
+
+    it("rejects a proof against a revocation list of the voter's own", async function () {
+      const { submitter, relayed } = await loadFixture(deployFixture);
+      const round = fixture.relayed;
+      // the registrar has not published a revocation root
+      await openVoting(relayed, { ...round, revocationRoot: undefined });
+
+      await expect(relayed.connect(submitter).submitRelayedVote(round.votingRound, round.sn, [], proof, round.inputs))
+        .to.be.revertedWith("Proof is against another revocation list");
+    });
+
+    it("only takes revocation roots from the owner", async function () {
+      const { other, plain, relayed } = await loadFixture(deployFixture);
+      const round = fixture.relayed;
+      await openVoting(relayed, round);
+
+      await expect(relayed.connect(other).setRevocationRoot(round.votingRound, "1"))
+        .to.be.revertedWith("Only owner can call this function");
+      await expect(plain.setRevocationRoot(round.votingRound, "1"))
+        .to.be.revertedWith("Voters are not revoked in this election");
+    });
+
+    it("rejects a front-runner", async function () {
+      const { other, relayed } = await loadFixture(deployFixture);
+      const round = fixture.relayed;
+      await openVoting(relayed, round);
+
+      await expect(relayed.connect(other).submitRelayedVote(round.votingRound, round.sn, round.votes, proof, round.inputs))
+        .to.be.revertedWith("Proof is bound to another submitter");
+    });
+
+    it("is closed in an election without relayers", async function () {
+      const { submitter, plain } = await loadFixture(deployFixture);
+      const round = fixture.plain;
+      await openVoting(plain, round);
+
+      await expect(plain.connect(submitter).submitRelayedVote(round.votingRound, round.sn, [], proof, round.inputs))
+        .to.be.revertedWith("Votes are not relayed in this election");
+    });
+  });
+});